@@ -0,0 +1,57 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use toylang::lexer::Lexer;
+
+const LINES: usize = 100_000;
+
+/// Generates a program of `LINES` lines that exercises every kind of token.
+fn generate_source() -> String {
+    let snippets = [
+        "# compute the running total of the values",
+        "let total_{i} = 0",
+        "fun accumulate_{i}(values, offset)",
+        "\tlet index = 0",
+        "\twhile index < len(values) && offset >= 0",
+        "\t\ttotal_{i} = total_{i} + values[index] * 2.5 - offset / 4",
+        "\t\tindex = index + 1",
+        "\tend",
+        "\treturn total_{i}",
+        "end",
+        "let result_{i} = accumulate_{i}([1, 2, 3, 0xFF, 1_000], 3)",
+        "if !(result_{i} <= 10) || result_{i} == 42",
+        "\tprint(\"result: ${result_{i}} \\\"done\\\"\")",
+        "end",
+    ];
+
+    let mut source = String::new();
+    for i in 0..LINES {
+        source.push_str(
+            &snippets[i % snippets.len()].replace("{i}", &(i / snippets.len()).to_string()),
+        );
+        source.push('\n');
+    }
+
+    source
+}
+
+fn tokenize(c: &mut Criterion) {
+    let source = generate_source();
+
+    let mut group = c.benchmark_group("lexer");
+    group.throughput(Throughput::Bytes(source.len() as u64));
+    group.sample_size(10);
+    group.bench_function("tokenize 100k lines", |b| {
+        b.iter(|| Lexer::new().tokenize(&source).unwrap())
+    });
+    group.bench_function("stream 100k lines", |b| {
+        b.iter(|| {
+            Lexer::new()
+                .stream(source.as_bytes())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tokenize);
+criterion_main!(benches);
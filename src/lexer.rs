@@ -1,22 +1,57 @@
+pub mod error;
 pub mod token;
 
+mod builder;
+mod cursor;
+#[allow(clippy::module_inception)]
 mod lexer;
+mod relex;
+mod source_map;
+mod stream;
 mod tokenizers;
 
-pub use lexer::Lexer;
+pub use builder::LexerBuilder;
+pub use cursor::Cursor;
+pub use error::*;
+pub use lexer::{Lexer, Position};
+pub use relex::{TextEdit, TokenDelta};
+pub use source_map::{FileId, SourceLocation, SourceMap};
+pub use stream::TokenStream;
 pub use token::*;
+pub use tokenizers::Tokenizer;
 
 #[cfg(test)]
-mod tests {
-    use std::vec;
+pub(crate) mod tests {
+    use std::{
+        io::{self, BufReader, Read},
+        vec,
+    };
 
-    use crate::lexer::Lexer;
+    use proptest::{prelude::*, sample::Index};
 
-    use super::{Keyword, Token};
+    use crate::lexer::{Cursor, Lexer, SourceLocation, SourceMap, TextEdit, Tokenizer};
+
+    use super::{
+        Keyword, LexError, LexErrorKind, TemplateFragment, Token, TokenSpan, TokenWrapper,
+        TriviaPiece,
+    };
+
+    /// The span of `len` bytes of ASCII text, all on the same line.
+    fn span(line: usize, column: usize, start: usize, len: usize) -> TokenSpan {
+        TokenSpan {
+            line,
+            column,
+            start,
+            len,
+            end_line: line,
+            end_column: column + len,
+            ..Default::default()
+        }
+    }
 
     /// Helper function which makes sure that the tokens of a source code are the expected ones.
     fn verify_tokens(contents: &str, expected_tokens: Vec<Token>) {
-        let tokens = Lexer::tokenize(contents.to_owned());
+        let tokens = Lexer::new().tokenize(contents).unwrap();
 
         assert_eq!(tokens.len(), expected_tokens.len());
 
@@ -46,6 +81,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn keyword_prefixed_identifiers() {
+        verify_tokens(
+            "letter endpoint iffy end",
+            vec![
+                Token::Identifier("letter".to_owned()),
+                Token::Identifier("endpoint".to_owned()),
+                Token::Identifier("iffy".to_owned()),
+                Token::Keyword(Keyword::End),
+            ],
+        );
+    }
+
     #[test]
     fn comment_test() {
         verify_tokens(
@@ -120,4 +168,735 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn range_operators() {
+        verify_tokens(
+            "0..n 1..=.5 step x.y",
+            vec![
+                Token::NumberLiteral(0.0),
+                Token::DotDot,
+                Token::Identifier("n".to_owned()),
+                Token::NumberLiteral(1.0),
+                Token::DotDotEq,
+                Token::NumberLiteral(0.5),
+                Token::Identifier("step".to_owned()),
+                Token::Identifier("x".to_owned()),
+                Token::Dot,
+                Token::Identifier("y".to_owned()),
+            ],
+        );
+    }
+
+    #[test]
+    fn errors_are_collected() {
+        let errors = Lexer::new()
+            .tokenize("let a = @\nlet b = 12abc\nprint(\"oops)")
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (e.kind, e.span))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            errors,
+            vec![
+                (LexErrorKind::UnknownCharacter('@'), span(0, 8, 8, 1)),
+                (
+                    LexErrorKind::MalformedNumber("12abc".to_owned()),
+                    span(1, 8, 18, 5)
+                ),
+                (LexErrorKind::UnterminatedString, span(2, 6, 30, 6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_track_lines_and_offsets() {
+        let spans = Lexer::new()
+            .tokenize("let a = \"hi\"\n\n  print(a)")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.span)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            spans,
+            vec![
+                span(0, 0, 0, 3),
+                span(0, 4, 4, 1),
+                span(0, 6, 6, 1),
+                span(0, 8, 8, 4),
+                span(2, 2, 16, 5),
+                span(2, 7, 21, 1),
+                span(2, 8, 22, 1),
+                span(2, 9, 23, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        verify_tokens(
+            r#""a\"b\\c\nd\te\0 \x41 \u{3bb}\u{1F600}""#,
+            vec![Token::StringLiteral(
+                "a\"b\\c\nd\te\0 A \u{3bb}\u{1F600}".to_owned(),
+            )],
+        );
+    }
+
+    #[test]
+    fn raw_and_multiline_strings() {
+        let source = r#"let a = r"C:\dir\${x}" + """
+    SELECT *
+      FROM t
+    \t
+    WHERE a = "${b}"
+    """ + r"""\d+
+  \w"""
+print(a)"#;
+        let tokens = Lexer::new().tokenize(source).unwrap();
+
+        assert_eq!(
+            tokens[3].token,
+            Token::StringLiteral(r"C:\dir\${x}".to_owned())
+        );
+        assert_eq!(tokens[3].span, span(0, 8, 8, 14));
+        assert_eq!(
+            tokens[5].token,
+            Token::Template(vec![
+                TemplateFragment::Text("SELECT *\n  FROM t\n\t\nWHERE a = \"".to_owned()),
+                TemplateFragment::Expression(vec![TokenWrapper::new(
+                    Token::Identifier("b".to_owned()),
+                    span(4, 17, 79, 1)
+                )]),
+                TemplateFragment::Text("\"".to_owned()),
+            ])
+        );
+        assert_eq!(
+            tokens[5].span,
+            TokenSpan {
+                end_line: 5,
+                end_column: 7,
+                ..span(0, 25, 25, 65)
+            }
+        );
+        assert_eq!(
+            tokens[7].token,
+            Token::StringLiteral("\\d+\n  \\w".to_owned())
+        );
+        assert_eq!(tokens[8].span, span(7, 0, 109, 5));
+
+        // Windows line breaks are read as a single `\n`
+        let tokens = Lexer::new()
+            .tokenize("\"\"\"\r\n  a\r\n  b\r\n\"\"\" r\"\"\"\r\n  \\d\r\n\r\n  c\r\n  \"\"\"")
+            .unwrap();
+        assert_eq!(tokens[0].token, Token::StringLiteral("a\nb".to_owned()));
+        assert_eq!(tokens[1].token, Token::StringLiteral("\\d\n\nc".to_owned()));
+
+        let errors = Lexer::new().tokenize("a\n\"\"\"\nb\nc").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexError::new(
+                LexErrorKind::UnterminatedString,
+                span(1, 0, 2, 3)
+            )]
+        );
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        let errors = Lexer::new()
+            .tokenize(r#"let s = "a\qb \xZ1 \u{110000} \u12""#)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (e.kind, e.span))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            errors,
+            vec![
+                (LexErrorKind::InvalidEscape, span(0, 11, 11, 1)),
+                (LexErrorKind::InvalidEscape, span(0, 16, 16, 1)),
+                (LexErrorKind::InvalidEscape, span(0, 22, 22, 6)),
+                (LexErrorKind::InvalidEscape, span(0, 32, 32, 1)),
+            ]
+        );
+
+        // The quote or line break where a brace was expected still ends the string
+        let errors = Lexer::new().tokenize(r#"let s = "\u{41" + x"#).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexError::new(
+                LexErrorKind::InvalidEscape,
+                span(0, 14, 14, 1)
+            )]
+        );
+
+        let source = "\"\\u{e\nlet a = 1\n";
+        let errors = Lexer::new().tokenize(source).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                LexError::new(LexErrorKind::InvalidEscape, span(0, 5, 5, 0)),
+                LexError::new(LexErrorKind::UnterminatedString, span(0, 0, 0, 5)),
+            ]
+        );
+
+        let streamed = Lexer::new()
+            .stream(BufReader::with_capacity(4, source.as_bytes()))
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+        assert_eq!(streamed, errors);
+    }
+
+    #[test]
+    fn string_template() {
+        let tokens = Lexer::new()
+            .tokenize(r#""total: ${a + "}"} and ${"${b}"}\${c}""#)
+            .unwrap();
+        let Token::Template(fragments) = &tokens[0].token else {
+            panic!("expected a template, got {:?}", tokens[0].token);
+        };
+
+        let fragments = fragments
+            .iter()
+            .map(|f| match f {
+                TemplateFragment::Text(text) => Ok(text.clone()),
+                TemplateFragment::Expression(tokens) => {
+                    Err(tokens.iter().map(|t| t.token.clone()).collect::<Vec<_>>())
+                }
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(
+            fragments,
+            vec![
+                Ok("total: ".to_owned()),
+                Err(vec![
+                    Token::Identifier("a".to_owned()),
+                    Token::Plus,
+                    Token::StringLiteral("}".to_owned()),
+                ]),
+                Ok(" and ".to_owned()),
+                Err(vec![Token::Template(vec![TemplateFragment::Expression(
+                    vec![TokenWrapper::new(
+                        Token::Identifier("b".to_owned()),
+                        span(0, 28, 28, 1),
+                    )]
+                )])]),
+                Ok("${c}".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_interpolation() {
+        let errors = Lexer::new()
+            .tokenize("\"a ${b\nprint(1)")
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (e.kind, e.span))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            errors,
+            vec![(LexErrorKind::UnterminatedInterpolation, span(0, 3, 3, 3))]
+        );
+    }
+
+    #[test]
+    fn number_literals() {
+        verify_tokens(
+            "1 1.5 .5 1e6 2.5E-3 1_000_000 0xFF 0Xff 0b1010 0o17 0xdead_beef",
+            vec![
+                Token::NumberLiteral(1.0),
+                Token::NumberLiteral(1.5),
+                Token::NumberLiteral(0.5),
+                Token::NumberLiteral(1e6),
+                Token::NumberLiteral(2.5e-3),
+                Token::NumberLiteral(1_000_000.0),
+                Token::NumberLiteral(255.0),
+                Token::NumberLiteral(255.0),
+                Token::NumberLiteral(10.0),
+                Token::NumberLiteral(15.0),
+                Token::NumberLiteral(3735928559.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn malformed_number_literals() {
+        let literals = [
+            "1.", "1e", "1e+", "0x", "0xZZ", "0b102", "0o8", "1__0", "1_", "1_e5", "1.2.3", "0x_1",
+        ];
+
+        for literal in literals {
+            let errors = Lexer::new()
+                .tokenize(&format!("{} + 1", literal))
+                .unwrap_err();
+
+            assert_eq!(
+                errors,
+                vec![LexError::new(
+                    LexErrorKind::MalformedNumber(literal.to_owned()),
+                    span(0, 0, 0, literal.len())
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn unicode_source() {
+        verify_tokens(
+            "# σχόλιο με ελληνικά\nlet όνομα = \"καλημέρα κόσμε\"\nprint(όνομα)",
+            vec![
+                Token::Comment("# σχόλιο με ελληνικά".to_owned()),
+                Token::Keyword(Keyword::Let),
+                Token::Identifier("όνομα".to_owned()),
+                Token::Equals,
+                Token::StringLiteral("καλημέρα κόσμε".to_owned()),
+                Token::Identifier("print".to_owned()),
+                Token::OpenParens,
+                Token::Identifier("όνομα".to_owned()),
+                Token::CloseParens,
+            ],
+        );
+    }
+
+    #[test]
+    fn unicode_spans() {
+        let source = "let 名前 = \"λ\" € 1";
+        let mut errors = vec![];
+        let spans = Lexer::new()
+            .tokenize_range(source, Default::default(), &mut errors)
+            .into_iter()
+            .map(|t| t.span)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            spans,
+            vec![
+                span(0, 0, 0, 3),
+                TokenSpan {
+                    end_column: 8,
+                    ..span(0, 4, 4, 6)
+                },
+                span(0, 9, 11, 1),
+                TokenSpan {
+                    end_column: 14,
+                    ..span(0, 11, 13, 4)
+                },
+                span(0, 17, 22, 1),
+            ]
+        );
+        assert_eq!(
+            errors,
+            vec![LexError::new(
+                LexErrorKind::UnknownCharacter('€'),
+                TokenSpan {
+                    end_column: 16,
+                    ..span(0, 15, 18, 3)
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        let sources = [
+            "",
+            "   # only a comment\n\n",
+            "let a = 0xFF # trailing comment\n\n\t# leading comment\nprint(\"a\\n${a}\")  \n",
+            include_str!("../examples/scopes.toy"),
+            include_str!("../examples/array.toy"),
+            include_str!("../examples/functions.toy"),
+            include_str!("../examples/strings.toy"),
+        ];
+
+        for source in sources {
+            let tokens = Lexer::new().tokenize_lossless(source).unwrap();
+
+            assert_eq!(Lexer::to_source(&tokens), source);
+        }
+    }
+
+    #[test]
+    fn block_and_doc_comments() {
+        verify_tokens(
+            "#[ outer #[ inner ]# \n still ]# a ##  Docs ## here \n#[]#b",
+            vec![
+                Token::Comment("#[ outer #[ inner ]# \n still ]#".to_owned()),
+                Token::Identifier("a".to_owned()),
+                Token::DocComment(" Docs ## here".to_owned()),
+                Token::Comment("#[]#".to_owned()),
+                Token::Identifier("b".to_owned()),
+            ],
+        );
+
+        let errors = Lexer::new().tokenize("a\n  #[ #[ ]#\nb").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexError::new(
+                LexErrorKind::UnterminatedComment,
+                span(1, 2, 4, 2)
+            )]
+        );
+    }
+
+    #[test]
+    fn lossless_trivia() {
+        let tokens = Lexer::new()
+            .tokenize_lossless("a # one\n  # two\nb #[ x\n ]#  \n")
+            .unwrap();
+        let trivia = tokens
+            .iter()
+            .map(|t| {
+                let trivia = t.trivia.as_ref().unwrap();
+                let texts = |pieces: &Vec<TriviaPiece>| {
+                    pieces.iter().map(|p| p.text.clone()).collect::<Vec<_>>()
+                };
+
+                (
+                    t.token.clone(),
+                    texts(&trivia.leading),
+                    texts(&trivia.trailing),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            trivia,
+            vec![
+                (
+                    Token::Identifier("a".to_owned()),
+                    vec![],
+                    vec![" ".to_owned(), "# one".to_owned()]
+                ),
+                (
+                    Token::Identifier("b".to_owned()),
+                    vec!["\n  ".to_owned(), "# two".to_owned(), "\n".to_owned()],
+                    vec![" ".to_owned(), "#[ x\n ]#".to_owned()]
+                ),
+                (Token::EndOfFile, vec!["  \n".to_owned()], vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn source_map() {
+        let mut sources = SourceMap::new();
+        let main = sources.add("main.toy", "print(1)\n");
+        let lib = sources.add("lib.toy", "let a = 1\r\n\tlet 名前 = @\n");
+
+        let tokens = Lexer::new().tokenize_file(&sources, main).unwrap();
+        assert!(tokens.iter().all(|t| t.span.file == main));
+
+        let error = Lexer::new()
+            .tokenize_file(&sources, lib)
+            .unwrap_err()
+            .pop()
+            .unwrap();
+        assert_eq!(error.span.file, lib);
+        assert_eq!(
+            sources.resolve(&error.span),
+            SourceLocation {
+                name: "lib.toy",
+                line: 2,
+                column: 13,
+                text: "\tlet 名前 = @",
+            }
+        );
+        assert_eq!(
+            sources.render(&error.span, &error.kind),
+            "lib.toy:2:13: unknown character '@'\n  2 | \tlet 名前 = @\n    | \t           ^"
+        );
+
+        // Tokens relexed from the start of a file still point to it
+        let other = sources.add("other.toy", "  x y");
+        let lexer = Lexer::new();
+        let mut tokens = lexer.tokenize_file(&sources, other).unwrap();
+        let edit = TextEdit::new(0..0, "q ");
+        lexer
+            .relex(&mut tokens, &edit.apply(sources.contents(other)), &edit)
+            .unwrap();
+
+        assert_eq!(tokens[0].token, Token::Identifier("q".to_owned()));
+        assert!(tokens.iter().all(|t| t.span.file == other));
+    }
+
+    /// Returns its contents a few bytes at a time, and then fails.
+    struct FailingReader(&'static [u8]);
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::other("disconnected"));
+            }
+
+            let len = buf.len().min(self.0.len()).min(3);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn token_stream() {
+        let source = concat!(
+            "let a = \"\"\"\n  one\n  ${b}\n  \"\"\" # c\n",
+            "#[ nested #[\n]# ]# print(a, 1.5)\n",
+            "\t\"λ ${x + 1}\"\n\n"
+        );
+
+        let streamed = Lexer::new()
+            .stream(BufReader::with_capacity(4, source.as_bytes()))
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(streamed, Ok(Lexer::new().tokenize(source).unwrap()));
+
+        let mut stream = Lexer::new().stream(BufReader::new(FailingReader(b"a @ b\n1\n")));
+        let items = stream.by_ref().take(4).collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                Ok(TokenWrapper::new(
+                    Token::Identifier("a".to_owned()),
+                    span(0, 0, 0, 1)
+                )),
+                Err(LexError::new(
+                    LexErrorKind::UnknownCharacter('@'),
+                    span(0, 2, 2, 1)
+                )),
+                Ok(TokenWrapper::new(
+                    Token::Identifier("b".to_owned()),
+                    span(0, 4, 4, 1)
+                )),
+                Ok(TokenWrapper::new(
+                    Token::NumberLiteral(1.0),
+                    span(1, 0, 6, 1)
+                )),
+            ]
+        );
+        assert_eq!(
+            stream.next(),
+            Some(Err(LexError::new(
+                LexErrorKind::Unreadable("disconnected".to_owned()),
+                span(2, 0, 8, 0)
+            )))
+        );
+        assert_eq!(stream.next(), None);
+    }
+
+    #[test]
+    fn relex_shifts_later_tokens() {
+        let source = "let a = 1\nlet b = a + 2";
+        let lexer = Lexer::new();
+        let mut tokens = lexer.tokenize(source).unwrap();
+
+        let edit = TextEdit::new(8..9, "100");
+        let delta = lexer
+            .relex(&mut tokens, &edit.apply(source), &edit)
+            .unwrap();
+
+        assert_eq!(delta.shift, 2);
+        assert_eq!(delta.removed, 2..4);
+        assert_eq!(delta.inserted, 2);
+        assert_eq!(tokens[3].token, Token::NumberLiteral(100.0));
+        assert_eq!(tokens[4].span, span(1, 0, 12, 3));
+    }
+
+    /// Durations like `10ms`, which would otherwise be malformed numbers.
+    pub(crate) struct DurationTokenizer;
+
+    impl Tokenizer for DurationTokenizer {
+        fn tokenize(&self, cursor: &mut Cursor, _: &mut Vec<LexError>) -> Option<Token> {
+            let digits = cursor.rest().find(|c: char| !c.is_ascii_digit())?;
+            if digits == 0 || !cursor.rest()[digits..].starts_with("ms") {
+                return None;
+            }
+
+            let text = cursor.rest()[..digits + 2].to_owned();
+            cursor.advance(text.len());
+
+            Some(Token::Custom {
+                kind: "duration",
+                text,
+            })
+        }
+    }
+
+    /// Annotations like `@inline`.
+    pub(crate) struct AnnotationTokenizer;
+
+    impl Tokenizer for AnnotationTokenizer {
+        fn tokenize(&self, cursor: &mut Cursor, _: &mut Vec<LexError>) -> Option<Token> {
+            cursor.peek().filter(|&c| c == '@')?;
+            cursor.bump();
+
+            Some(Token::Custom {
+                kind: "annotation",
+                text: cursor.eat_while(char::is_alphanumeric).to_owned(),
+            })
+        }
+    }
+
+    #[test]
+    fn custom_tokenizers() {
+        let lexer = Lexer::builder()
+            .tokenizer(-1, AnnotationTokenizer)
+            .tokenizer(1, DurationTokenizer)
+            .build();
+        let custom = |kind, text: &str| Token::Custom {
+            kind,
+            text: text.to_owned(),
+        };
+
+        let tokens = lexer
+            .tokenize(r#"@retry sleep(10ms, "${5ms}") 10"#)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            tokens,
+            vec![
+                custom("annotation", "retry"),
+                Token::Identifier("sleep".to_owned()),
+                Token::OpenParens,
+                custom("duration", "10ms"),
+                Token::Comma,
+                Token::Template(vec![TemplateFragment::Expression(vec![TokenWrapper::new(
+                    custom("duration", "5ms"),
+                    span(0, 22, 22, 3)
+                )])]),
+                Token::CloseParens,
+                Token::NumberLiteral(10.0),
+            ]
+        );
+
+        // The built-in tokens are still the same without the custom tokenizers
+        let errors = Lexer::new().tokenize("@retry 10ms").unwrap_err();
+        assert_eq!(errors[0].kind, LexErrorKind::UnknownCharacter('@'));
+        assert_eq!(
+            errors[1].kind,
+            LexErrorKind::MalformedNumber("10ms".to_owned())
+        );
+    }
+
+    /// Returns a token for `@` without consuming it.
+    struct StuckTokenizer;
+
+    impl Tokenizer for StuckTokenizer {
+        fn tokenize(&self, cursor: &mut Cursor, _: &mut Vec<LexError>) -> Option<Token> {
+            cursor.peek().filter(|&c| c == '@')?;
+
+            Some(Token::Custom {
+                kind: "stuck",
+                text: String::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn empty_custom_tokens() {
+        let lexer = Lexer::builder().tokenizer(1, StuckTokenizer).build();
+
+        let errors = lexer
+            .tokenize("a @")
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (e.kind, e.span))
+            .collect::<Vec<_>>();
+
+        let stuck = Token::Custom {
+            kind: "stuck",
+            text: String::new(),
+        };
+        assert_eq!(
+            errors,
+            vec![
+                (LexErrorKind::EmptyToken(stuck), span(0, 2, 2, 0)),
+                (LexErrorKind::UnknownCharacter('@'), span(0, 2, 2, 1)),
+            ]
+        );
+    }
+
+    /// Pieces of source code that are valid in any order, combined to generate the sources to
+    /// edit. Numbers end with a space, since `1x` would be a malformed number.
+    const PIECES: &[&str] = &[
+        "let",
+        "letter",
+        "end",
+        "x",
+        "λ",
+        "名前",
+        " ",
+        "  ",
+        "\n",
+        "\t",
+        "1 ",
+        "0xF ",
+        "1.5 ",
+        "5 ",
+        "e",
+        "_",
+        "=",
+        "==",
+        "<",
+        "&&",
+        "(",
+        ")",
+        "[",
+        "]",
+        ",",
+        "+",
+        "-",
+        "# c\n",
+        "## d\n",
+        "#[ c ]#",
+        "#[ a #[\nb ]# c ]#",
+        "\"s\"",
+        " r\"\\d\"",
+        "\"\"\"\n  m\n  ${x}\"\"\"",
+        "\"a ${b + \"c\"}\"",
+    ];
+
+    /// Pieces inserted by the edits, which may also make the source invalid.
+    const EDIT_PIECES: &[&str] = &[
+        "a", "let", " ", "\n", "1", "0x", ".", "e", "_", "=", "&", "|", "#", "\"", "\\", "${", "}",
+        "@", "λ", "#[", "]#",
+    ];
+
+    fn floor_char_boundary(source: &str, mut index: usize) -> usize {
+        while !source.is_char_boundary(index) {
+            index -= 1;
+        }
+
+        index
+    }
+
+    proptest! {
+        #[test]
+        fn relex_matches_full_lex(
+            source in prop::collection::vec(prop::sample::select(PIECES), 0..40),
+            start in any::<Index>(),
+            len in any::<Index>(),
+            text in prop::collection::vec(prop::sample::select(EDIT_PIECES), 0..4),
+        ) {
+            let source = source.concat();
+            let lexer = Lexer::new();
+            let mut tokens = lexer.tokenize(&source).unwrap();
+
+            let start = floor_char_boundary(&source, start.index(source.len() + 1));
+            let end = floor_char_boundary(&source, start + len.index(source.len() - start + 1));
+            let edit = TextEdit::new(start..end, &text.concat());
+            let edited = edit.apply(&source);
+
+            let relexed = lexer.relex(&mut tokens, &edited, &edit).map(|_| tokens);
+
+            prop_assert_eq!(relexed, lexer.tokenize(&edited));
+        }
+    }
 }
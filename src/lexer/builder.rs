@@ -0,0 +1,66 @@
+use super::{Lexer, Tokenizer};
+
+/// Builds a [`Lexer`] that recognizes extra tokens, on top of the ones of the language, for
+/// example to embed toylang in a DSL:
+///
+/// ```
+/// use toylang::lexer::{Cursor, LexError, Lexer, Token, Tokenizer};
+///
+/// struct AnnotationTokenizer;
+///
+/// impl Tokenizer for AnnotationTokenizer {
+///     fn tokenize(&self, cursor: &mut Cursor, _: &mut Vec<LexError>) -> Option<Token> {
+///         cursor.peek().filter(|&c| c == '@')?;
+///         cursor.bump();
+///         let name = cursor.eat_while(char::is_alphanumeric);
+///
+///         Some(Token::Custom {
+///             kind: "annotation",
+///             text: name.to_owned(),
+///         })
+///     }
+/// }
+///
+/// let lexer = Lexer::builder().tokenizer(-1, AnnotationTokenizer).build();
+/// let tokens = lexer.tokenize("@inline").unwrap();
+///
+/// assert_eq!(
+///     tokens[0].token,
+///     Token::Custom {
+///         kind: "annotation",
+///         text: "inline".to_owned()
+///     }
+/// );
+/// ```
+#[derive(Default)]
+pub struct LexerBuilder {
+    tokenizers: Vec<(i32, Box<dyn Tokenizer>)>,
+}
+
+impl LexerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a custom tokenizer. Tokenizers with a non-negative `priority` are tried before the
+    /// built-in ones, so they may take over input the language already recognizes, like the
+    /// `ms` suffix in `10ms`. Tokenizers with a negative `priority` are only tried when none of
+    /// the built-in ones recognizes the input. Higher priorities are tried first, and
+    /// tokenizers with the same priority in the order they were added.
+    ///
+    /// [`Lexer::relex`] assumes that tokens never depend on more than the two characters after
+    /// them, which custom tokenizers should respect as well.
+    pub fn tokenizer(mut self, priority: i32, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizers.push((priority, Box::new(tokenizer)));
+        self
+    }
+
+    pub fn build(mut self) -> Lexer {
+        self.tokenizers
+            .sort_by_key(|(priority, _)| std::cmp::Reverse(*priority));
+
+        Lexer {
+            tokenizers: self.tokenizers,
+        }
+    }
+}
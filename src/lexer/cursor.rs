@@ -0,0 +1,75 @@
+use super::{Position, TokenSpan};
+
+/// A view into the source code that keeps track of the position while it is being scanned.
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    contents: &'a str,
+    position: Position,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(contents: &'a str, position: Position) -> Self {
+        Cursor { contents, position }
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// The whole source code, including the parts that were already scanned.
+    pub fn contents(&self) -> &'a str {
+        self.contents
+    }
+
+    /// The source code that has not been scanned yet.
+    pub fn rest(&self) -> &'a str {
+        &self.contents[self.position.offset..]
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    pub fn peek_second(&self) -> Option<char> {
+        self.rest().chars().nth(1)
+    }
+
+    pub fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.advance(c.len_utf8());
+
+        Some(c)
+    }
+
+    /// Moves the cursor `amount` bytes forward.
+    pub fn advance(&mut self, amount: usize) {
+        self.position.advance(self.contents, amount);
+    }
+
+    /// Consumes characters for as long as `predicate` holds, returning the consumed text.
+    pub fn eat_while(&mut self, mut predicate: impl FnMut(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.advance(len);
+
+        &rest[..len]
+    }
+
+    /// The position `offset` bytes after the cursor, used to point inside of a token.
+    pub fn position_at(&self, offset: usize) -> Position {
+        let mut position = self.position;
+        position.advance(self.contents, offset);
+
+        position
+    }
+
+    /// The span between `start` and the cursor.
+    pub fn span_from(&self, start: &Position) -> TokenSpan {
+        TokenSpan::new(start, &self.position)
+    }
+
+    /// The span of the `len` bytes starting `offset` bytes after the cursor.
+    pub fn span_at(&self, offset: usize, len: usize) -> TokenSpan {
+        TokenSpan::new(&self.position_at(offset), &self.position_at(offset + len))
+    }
+}
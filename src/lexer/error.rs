@@ -0,0 +1,64 @@
+use std::fmt::Display;
+
+use super::{Token, TokenSpan};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexErrorKind {
+    UnknownCharacter(char),
+    UnterminatedString,
+    InvalidEscape,
+    UnterminatedInterpolation,
+    MalformedNumber(String),
+    UnterminatedComment,
+    /// Reading the source failed, with the given reason
+    Unreadable(String),
+    /// A custom tokenizer returned this token without moving the cursor
+    EmptyToken(Token),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: TokenSpan,
+}
+
+impl LexError {
+    pub fn new(kind: LexErrorKind, span: TokenSpan) -> Self {
+        LexError { kind, span }
+    }
+}
+
+impl Display for LexErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexErrorKind::UnknownCharacter(c) => write!(f, "unknown character {:?}", c),
+            LexErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            LexErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            LexErrorKind::UnterminatedInterpolation => {
+                write!(f, "unterminated string interpolation")
+            }
+            LexErrorKind::MalformedNumber(text) => write!(f, "malformed number {:?}", text),
+            LexErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+            LexErrorKind::Unreadable(reason) => write!(f, "unable to read the source: {}", reason),
+            LexErrorKind::EmptyToken(token) => {
+                write!(
+                    f,
+                    "a tokenizer returned `{}` without consuming any input",
+                    token
+                )
+            }
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} [line: {}, column: {}]",
+            self.kind,
+            self.span.line + 1,
+            self.span.column + 1
+        )
+    }
+}
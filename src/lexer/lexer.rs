@@ -1,66 +1,305 @@
+use std::io::BufRead;
+
+use unicode_width::UnicodeWidthChar;
+
 use super::{
     tokenizers::{
-        CharTokenizer, CommentTokenizer, IdentifierTokenizer, KeywordTokenizer, NumberTokenizer,
-        StringTokenizer, Tokenizer, WhitespaceTokenizer,
+        CharTokenizer, CommentTokenizer, IdentifierTokenizer, NumberTokenizer, StringTokenizer,
+        Tokenizer, WhitespaceTokenizer,
     },
-    Token, TokenWrapper,
+    Cursor, FileId, LexError, LexErrorKind, LexerBuilder, SourceMap, Token, TokenSpan, TokenStream,
+    TokenWrapper, Trivia, TriviaKind, TriviaPiece,
 };
 
-pub struct Lexer;
+/// Turns source code into tokens. [`Lexer::new`] only knows the tokens of the language itself,
+/// while [`Lexer::builder`] allows adding custom tokenizers.
+#[derive(Default)]
+pub struct Lexer {
+    /// The custom tokenizers, ordered by decreasing priority
+    pub(super) tokenizers: Vec<(i32, Box<dyn Tokenizer>)>,
+}
 
+/// A location in the source code. `offset` is in bytes, while `column` is the display column,
+/// so wide characters (like most CJK characters) count as two columns, and combining marks as
+/// none. Control characters, including tabs, count as a single column.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Position {
-    pub line: usize,
+    pub file: FileId,
     pub offset: usize,
+    pub line: usize,
+    pub column: usize,
 }
 
 impl Position {
-    pub fn advance(&mut self, amount: usize) {
+    /// Moves the position `amount` bytes forward, keeping track of the lines crossed.
+    pub fn advance(&mut self, contents: &str, amount: usize) {
+        for c in contents[self.offset..self.offset + amount].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                self.column += c.width().unwrap_or(1);
+            }
+        }
+
         self.offset += amount;
     }
 }
 
 impl Lexer {
-    pub fn tokenize(source_code: String) -> Vec<TokenWrapper> {
-        let cloned = source_code.clone();
-        let lines = cloned.split('\n');
-
-        let tokenizers: Vec<Box<dyn Tokenizer>> = vec![
-            Box::new(CommentTokenizer::new()),
-            Box::new(KeywordTokenizer::new()),
-            Box::new(CharTokenizer::new()),
-            Box::new(StringTokenizer::new()),
-            Box::new(IdentifierTokenizer::new()),
-            Box::new(NumberTokenizer::new()),
-            Box::new(WhitespaceTokenizer::new()),
-        ];
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        let mut tokens = vec![];
-        for (line, contents) in lines.enumerate() {
-            let mut position = Position { line, offset: 0 };
-            'line: loop {
-                let view: &str = &contents[position.offset..contents.len()];
-                if view.len() == 0 {
-                    break;
+    pub fn builder() -> LexerBuilder {
+        LexerBuilder::new()
+    }
+
+    /// Tokenizes the whole source code. Lexing does not stop on the first invalid token, so
+    /// that every lexical error of the source is reported at once.
+    pub fn tokenize(&self, source_code: &str) -> Result<Vec<TokenWrapper>, Vec<LexError>> {
+        self.tokenize_at(source_code, Position::default())
+    }
+
+    /// Tokenizes a file registered in `sources`, so that the spans point back to it.
+    pub fn tokenize_file(
+        &self,
+        sources: &SourceMap,
+        file: FileId,
+    ) -> Result<Vec<TokenWrapper>, Vec<LexError>> {
+        let start = Position {
+            file,
+            ..Default::default()
+        };
+
+        self.tokenize_at(sources.contents(file), start)
+    }
+
+    fn tokenize_at(
+        &self,
+        source_code: &str,
+        start: Position,
+    ) -> Result<Vec<TokenWrapper>, Vec<LexError>> {
+        let mut errors = vec![];
+        let tokens = self.tokenize_range(source_code, start, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(tokens)
+    }
+
+    /// Tokenizes the source code of `reader` lazily, see [`TokenStream`].
+    pub fn stream<R: BufRead>(self, reader: R) -> TokenStream<R> {
+        TokenStream::new(self, reader)
+    }
+
+    /// Tokenizes the whole source code, keeping the whitespace and comments as trivia of the
+    /// tokens around them, so that [`Lexer::to_source`] can rebuild the exact source. The last
+    /// token is always a [`Token::EndOfFile`], holding the trivia at the end of the source.
+    pub fn tokenize_lossless(&self, source_code: &str) -> Result<Vec<TokenWrapper>, Vec<LexError>> {
+        let mut errors = vec![];
+        let mut cursor = Cursor::new(source_code, Position::default());
+        let mut tokens: Vec<TokenWrapper> = vec![];
+        let mut leading = vec![];
+        // Whether the trivia still belong to the previous token, as they are on the same line
+        let mut trailing = false;
+
+        while let Some(character) = cursor.peek() {
+            let start = cursor.position();
+
+            let kind = match self.scan(&mut cursor, &mut errors) {
+                Some(Token::Whitespace) => TriviaKind::Whitespace,
+                Some(Token::Comment(_)) => TriviaKind::Comment,
+                Some(token) => {
+                    let span = cursor.span_from(&start);
+                    let trivia = Trivia {
+                        text: source_code[span.start..span.start + span.len].to_owned(),
+                        leading: std::mem::take(&mut leading),
+                        trailing: vec![],
+                    };
+
+                    tokens.push(TokenWrapper {
+                        token,
+                        span,
+                        trivia: Some(Box::new(trivia)),
+                    });
+                    trailing = true;
+                    continue;
                 }
+                None => {
+                    cursor.bump();
+                    errors.push(LexError::new(
+                        LexErrorKind::UnknownCharacter(character),
+                        cursor.span_from(&start),
+                    ));
+                    continue;
+                }
+            };
 
-                for t in &tokenizers {
-                    if let Some(wrapper) = t.tokenize(contents, &position) {
-                        position.advance(wrapper.len());
-                        tokens.push(wrapper);
-                        continue 'line;
-                    }
+            let text = &source_code[start.offset..cursor.position().offset];
+            let piece = |text: &str, start: Position| {
+                let mut end = start;
+                end.advance(source_code, text.len());
+
+                TriviaPiece {
+                    kind,
+                    text: text.to_owned(),
+                    span: TokenSpan::new(&start, &end),
                 }
+            };
+
+            let previous = tokens.last_mut().and_then(|t| t.trivia.as_mut());
+            match previous {
+                Some(previous) if trailing => {
+                    // The line ends within this piece, so the rest of it belongs to the next
+                    // token. Block comments spanning several lines are kept whole, though.
+                    let newline = text.find('\n');
+                    let split = match (kind, newline) {
+                        (TriviaKind::Comment, Some(_)) => text.len(),
+                        (_, newline) => newline.unwrap_or(text.len()),
+                    };
 
-                panic!(
-                    "Unable to generate a token from {:?} [line: {}]",
-                    view, position.offset
-                );
+                    if split > 0 {
+                        previous.trailing.push(piece(&text[..split], start));
+                    }
+                    if split < text.len() {
+                        let mut position = start;
+                        position.advance(source_code, split);
+                        leading.push(piece(&text[split..], position));
+                    }
+                    trailing = newline.is_none();
+                }
+                _ => leading.push(piece(text, start)),
             }
         }
 
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let end = cursor.position();
+        tokens.push(TokenWrapper {
+            token: Token::EndOfFile,
+            span: TokenSpan::new(&end, &end),
+            trivia: Some(Box::new(Trivia {
+                leading,
+                ..Default::default()
+            })),
+        });
+
+        Ok(tokens)
+    }
+
+    /// Rebuilds the source code from the tokens of [`Lexer::tokenize_lossless`].
+    pub fn to_source(tokens: &[TokenWrapper]) -> String {
+        let mut source = String::new();
+
+        for trivia in tokens.iter().filter_map(|t| t.trivia.as_ref()) {
+            trivia.leading.iter().for_each(|p| source.push_str(&p.text));
+            source.push_str(&trivia.text);
+            trivia
+                .trailing
+                .iter()
+                .for_each(|p| source.push_str(&p.text));
+        }
+
+        source
+    }
+
+    /// Tokenizes `contents` starting from `position`. Used for the whole source, as well as
+    /// for the expressions embedded in string templates.
+    pub(super) fn tokenize_range(
+        &self,
+        contents: &str,
+        position: Position,
+        errors: &mut Vec<LexError>,
+    ) -> Vec<TokenWrapper> {
+        let mut cursor = Cursor::new(contents, position);
+        let mut tokens = vec![];
+
+        while let Some(token) = self.next_token(&mut cursor, errors) {
+            tokens.push(token);
+        }
+
         tokens
-            .into_iter()
-            .filter(|t| t.token != Token::Whitespace)
-            .collect()
+    }
+
+    /// Returns the next token after the cursor, skipping whitespace and unknown characters.
+    pub(super) fn next_token(
+        &self,
+        cursor: &mut Cursor,
+        errors: &mut Vec<LexError>,
+    ) -> Option<TokenWrapper> {
+        while let Some(character) = cursor.peek() {
+            let start = cursor.position();
+
+            match self.scan(cursor, errors) {
+                Some(Token::Whitespace) => {}
+                Some(token) => return Some(TokenWrapper::new(token, cursor.span_from(&start))),
+                None => {
+                    cursor.bump();
+                    errors.push(LexError::new(
+                        LexErrorKind::UnknownCharacter(character),
+                        cursor.span_from(&start),
+                    ));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Scans the next token. Custom tokenizers with a non-negative priority get the first try,
+    /// then the built-in ones, and the rest only get the input nothing else recognized.
+    fn scan(&self, cursor: &mut Cursor, errors: &mut Vec<LexError>) -> Option<Token> {
+        let split = self
+            .tokenizers
+            .partition_point(|(priority, _)| *priority >= 0);
+        let (before, after) = self.tokenizers.split_at(split);
+
+        Self::scan_custom(before, cursor, errors)
+            .or_else(|| self.scan_builtin(cursor, errors))
+            .or_else(|| Self::scan_custom(after, cursor, errors))
+    }
+
+    fn scan_custom(
+        tokenizers: &[(i32, Box<dyn Tokenizer>)],
+        cursor: &mut Cursor,
+        errors: &mut Vec<LexError>,
+    ) -> Option<Token> {
+        tokenizers.iter().find_map(|(_, tokenizer)| {
+            let start = cursor.position();
+            let token = tokenizer.tokenize(cursor, errors)?;
+
+            if cursor.position() == start {
+                let span = cursor.span_from(&start);
+                errors.push(LexError::new(LexErrorKind::EmptyToken(token), span));
+
+                return None;
+            }
+
+            Some(token)
+        })
+    }
+
+    /// Scans a token of the language itself, picking the tokenizer from the character at the
+    /// cursor.
+    fn scan_builtin(&self, cursor: &mut Cursor, errors: &mut Vec<LexError>) -> Option<Token> {
+        match cursor.peek()? {
+            c if c.is_whitespace() => WhitespaceTokenizer.tokenize(cursor, errors),
+            '#' => CommentTokenizer.tokenize(cursor, errors),
+            '"' => StringTokenizer { lexer: self }.tokenize(cursor, errors),
+            'r' if cursor.rest().starts_with("r\"") => {
+                StringTokenizer { lexer: self }.tokenize(cursor, errors)
+            }
+            '0'..='9' => NumberTokenizer.tokenize(cursor, errors),
+            '.' if cursor.peek_second().is_some_and(|c| c.is_ascii_digit()) => {
+                NumberTokenizer.tokenize(cursor, errors)
+            }
+            c if IdentifierTokenizer::is_start(c) => IdentifierTokenizer.tokenize(cursor, errors),
+            _ => CharTokenizer.tokenize(cursor, errors),
+        }
     }
 }
@@ -0,0 +1,161 @@
+use std::ops::Range;
+
+use super::{Cursor, LexError, Lexer, Position, TemplateFragment, Token, TokenWrapper};
+
+/// A change to the source code: the bytes in `range` are replaced with `text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: &str) -> Self {
+        TextEdit {
+            range,
+            text: text.to_owned(),
+        }
+    }
+
+    /// Applies the edit to `source`.
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = source.to_owned();
+        edited.replace_range(self.range.clone(), &self.text);
+
+        edited
+    }
+
+    /// How many bytes the text after the edit moves by.
+    pub fn shift(&self) -> isize {
+        self.text.len() as isize - self.range.len() as isize
+    }
+}
+
+/// Describes how the tokens changed after [`Lexer::relex`]: the old tokens in `removed` were
+/// replaced with `inserted` new tokens, and the spans of every token after them were shifted by
+/// `shift` bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenDelta {
+    pub removed: Range<usize>,
+    pub inserted: usize,
+    pub shift: isize,
+}
+
+impl Lexer {
+    /// Updates `tokens` after `edit` was applied to the source code, re-tokenizing only the
+    /// region around the edit. `source` is the source code after the edit, while the range of
+    /// `edit` refers to the source code before it.
+    ///
+    /// Lexing restarts right before the first token that touches the edit, and stops as soon as
+    /// a token starts after the edit at the same place an old token used to, since the lexer
+    /// would produce the same tokens from there on. On errors `tokens` are left untouched.
+    pub fn relex(
+        &self,
+        tokens: &mut Vec<TokenWrapper>,
+        source: &str,
+        edit: &TextEdit,
+    ) -> Result<TokenDelta, Vec<LexError>> {
+        let shift = edit.shift();
+        let edit_end = edit.range.start + edit.text.len();
+
+        // Tokenizers look up to two characters past the end of a token, so the token before the
+        // first one that touches the edit has to be lexed again as well
+        let first = tokens
+            .partition_point(|t| t.span.start + t.span.len < edit.range.start)
+            .saturating_sub(1);
+        let start = tokens
+            .get(first)
+            .filter(|t| t.span.start <= edit.range.start)
+            .map(|t| Position {
+                file: t.span.file,
+                offset: t.span.start,
+                line: t.span.line,
+                column: t.span.column,
+            })
+            .unwrap_or(Position {
+                // Lexing from the start of the source, which still belongs to the same file
+                file: tokens.first().map(|t| t.span.file).unwrap_or_default(),
+                ..Default::default()
+            });
+
+        let mut errors = vec![];
+        let mut cursor = Cursor::new(source, start);
+        let mut inserted = vec![];
+
+        // The first old token that is kept, along with where it has moved to
+        let (last, moved_to) = loop {
+            cursor.eat_while(char::is_whitespace);
+            let position = cursor.position();
+
+            if position.offset >= edit_end {
+                let old_offset = (position.offset as isize - shift) as usize;
+                let old = tokens[first..].binary_search_by_key(&old_offset, |t| t.span.start);
+
+                if let Ok(index) = old {
+                    break (first + index, Some(position));
+                }
+            }
+
+            match self.next_token(&mut cursor, &mut errors) {
+                Some(token) => inserted.push(token),
+                None => break (tokens.len(), None),
+            }
+        };
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        if let Some(position) = moved_to {
+            let old = &tokens[last].span;
+            let line_shift = position.line as isize - old.line as isize;
+            let column_shift = position.column as isize - old.column as isize;
+            let old_line = old.line;
+
+            for token in &mut tokens[last..] {
+                Self::shift_token(token, old_line, line_shift, column_shift, shift);
+            }
+        }
+
+        let delta = TokenDelta {
+            removed: first..last,
+            inserted: inserted.len(),
+            shift,
+        };
+        tokens.splice(first..last, inserted);
+
+        Ok(delta)
+    }
+
+    /// Moves a token that came after an edit. Only the tokens on the line where the edit ended
+    /// change columns.
+    pub(super) fn shift_token(
+        token: &mut TokenWrapper,
+        edit_line: usize,
+        line_shift: isize,
+        column_shift: isize,
+        shift: isize,
+    ) {
+        let span = &mut token.span;
+
+        if span.line == edit_line {
+            span.column = (span.column as isize + column_shift) as usize;
+        }
+        if span.end_line == edit_line {
+            span.end_column = (span.end_column as isize + column_shift) as usize;
+        }
+        span.line = (span.line as isize + line_shift) as usize;
+        span.end_line = (span.end_line as isize + line_shift) as usize;
+        span.start = (span.start as isize + shift) as usize;
+
+        if let Token::Template(fragments) = &mut token.token {
+            for fragment in fragments {
+                if let TemplateFragment::Expression(tokens) = fragment {
+                    for token in tokens {
+                        Self::shift_token(token, edit_line, line_shift, column_shift, shift);
+                    }
+                }
+            }
+        }
+    }
+}
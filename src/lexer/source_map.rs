@@ -0,0 +1,134 @@
+use std::fmt::Display;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::{Position, TokenSpan};
+
+/// Identifies a file, or a snippet of source code, registered in a [`SourceMap`]. Spans of
+/// sources that were not registered use the default id.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+#[derive(Debug)]
+struct SourceFile {
+    name: String,
+    contents: String,
+    /// The offset every line starts at
+    line_starts: Vec<usize>,
+}
+
+/// Keeps the source code of every file that was lexed, so that spans can be turned back into
+/// the file name, line and text to show in error messages.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+/// Where a span starts, as shown to the user: `line` and `column` are 1-based, and `text` is
+/// the whole line, without the line break.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation<'a> {
+    pub name: &'a str,
+    pub line: usize,
+    pub column: usize,
+    pub text: &'a str,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a file, or a snippet such as code typed in a prompt, returning its id.
+    pub fn add(&mut self, name: impl Into<String>, contents: impl Into<String>) -> FileId {
+        let contents = contents.into();
+        let line_starts = std::iter::once(0)
+            .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        self.files.push(SourceFile {
+            name: name.into(),
+            contents,
+            line_starts,
+        });
+
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    /// Panics if `file` was not registered in this map.
+    pub fn name(&self, file: FileId) -> &str {
+        &self.files[file.0 as usize].name
+    }
+
+    /// Panics if `file` was not registered in this map.
+    pub fn contents(&self, file: FileId) -> &str {
+        &self.files[file.0 as usize].contents
+    }
+
+    /// Panics if the file of `span` was not registered in this map.
+    pub fn resolve(&self, span: &TokenSpan) -> SourceLocation<'_> {
+        let file = &self.files[span.file.0 as usize];
+        let start = span.start.min(file.contents.len());
+
+        let line = file.line_starts.partition_point(|&s| s <= start) - 1;
+        let line_start = file.line_starts[line];
+        let line_end = file
+            .line_starts
+            .get(line + 1)
+            .map_or(file.contents.len(), |&next| next - 1);
+
+        let mut position = Position {
+            offset: line_start,
+            ..Default::default()
+        };
+        position.advance(&file.contents, start - line_start);
+
+        SourceLocation {
+            name: &file.name,
+            line: line + 1,
+            column: position.column + 1,
+            text: file.contents[line_start..line_end].trim_end_matches('\r'),
+        }
+    }
+
+    /// Formats `message` along with the location and line of `span`, underlining the span:
+    ///
+    /// ```text
+    /// main.toy:2:9: unknown character '@'
+    ///   2 | let b = @
+    ///     |         ^
+    /// ```
+    pub fn render(&self, span: &TokenSpan, message: impl Display) -> String {
+        let location = self.resolve(span);
+        let contents = self.contents(span.file);
+        let start = span.start.min(contents.len());
+        let line_start = contents[..start].rfind('\n').map_or(0, |i| i + 1);
+
+        // Tabs are kept, so that the underline lines up with the text above it
+        let padding = contents[line_start..start]
+            .chars()
+            .map(|c| match c {
+                '\t' => "\t".to_owned(),
+                c => " ".repeat(c.width().unwrap_or(1)),
+            })
+            .collect::<String>();
+
+        // The underline stops at the end of the line
+        let underlined = &contents[start..(start + span.len).min(contents.len())];
+        let underlined = underlined.split('\n').next().unwrap_or_default();
+        let number = location.line.to_string();
+
+        format!(
+            "{}:{}:{}: {}\n  {} | {}\n  {} | {}{}",
+            location.name,
+            location.line,
+            location.column,
+            message,
+            number,
+            location.text,
+            " ".repeat(number.len()),
+            padding,
+            "^".repeat(underlined.width().max(1)),
+        )
+    }
+}
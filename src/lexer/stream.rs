@@ -0,0 +1,128 @@
+use std::{collections::VecDeque, io::BufRead};
+
+use super::{Cursor, FileId, LexError, LexErrorKind, Lexer, Position, TokenSpan, TokenWrapper};
+
+/// Tokenizes the source code of a reader as it is being read, so that the first tokens are
+/// available before the whole source is. Lexical errors are returned in between the tokens,
+/// right before the token they were found in. Created by [`Lexer::stream`].
+pub struct TokenStream<R> {
+    lexer: Lexer,
+    reader: R,
+    /// The source code that was read but not compacted away yet
+    buffer: String,
+    /// How much of `buffer` was already tokenized
+    consumed: usize,
+    /// Where the part of `buffer` that was not tokenized yet starts in the whole source
+    position: Position,
+    pending: VecDeque<Result<TokenWrapper, LexError>>,
+    /// Whether the whole source was read
+    exhausted: bool,
+    /// Returned after the rest of the tokens, when reading fails
+    read_error: Option<LexError>,
+}
+
+impl<R: BufRead> TokenStream<R> {
+    pub fn new(lexer: Lexer, reader: R) -> Self {
+        TokenStream {
+            lexer,
+            reader,
+            buffer: String::new(),
+            consumed: 0,
+            position: Position::default(),
+            pending: VecDeque::new(),
+            exhausted: false,
+            read_error: None,
+        }
+    }
+
+    /// Makes the spans of the tokens point to `file`, for a source whose name was registered
+    /// in a [`SourceMap`](super::SourceMap).
+    pub fn in_file(mut self, file: FileId) -> Self {
+        self.position.file = file;
+        self
+    }
+
+    /// Reads whole lines, at least as much as the source that is still buffered, so that
+    /// tokens spanning many lines are not scanned again for every line.
+    fn read(&mut self) {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+
+        let wanted = self.buffer.len().max(1);
+        let mut read = 0;
+        while read < wanted {
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => self.exhausted = true,
+                Ok(len) => read += len,
+                Err(error) => {
+                    let mut end = Position {
+                        offset: 0,
+                        ..self.position
+                    };
+                    end.advance(&self.buffer, self.buffer.len());
+                    end.offset += self.position.offset;
+
+                    self.read_error = Some(LexError::new(
+                        LexErrorKind::Unreadable(error.to_string()),
+                        TokenSpan::new(&end, &end),
+                    ));
+                    self.exhausted = true;
+                }
+            }
+
+            if self.exhausted {
+                break;
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for TokenStream<R> {
+    type Item = Result<TokenWrapper, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+
+            let rest = &self.buffer[self.consumed..];
+            if rest.is_empty() && self.exhausted {
+                return self.read_error.take().map(Err);
+            }
+
+            let mut cursor = Cursor::new(
+                rest,
+                Position {
+                    offset: 0,
+                    ..self.position
+                },
+            );
+            let mut errors = vec![];
+            let token = self.lexer.next_token(&mut cursor, &mut errors);
+            let end = cursor.position();
+
+            // The token might go on in the part of the source that was not read yet
+            if end.offset == rest.len() && !self.exhausted {
+                self.read();
+                continue;
+            }
+
+            let shift = self.position.offset as isize;
+            for mut error in errors {
+                error.span.start += self.position.offset;
+                self.pending.push_back(Err(error));
+            }
+            if let Some(mut token) = token {
+                Lexer::shift_token(&mut token, 0, 0, 0, shift);
+                self.pending.push_back(Ok(token));
+            }
+
+            self.consumed += end.offset;
+            self.position = Position {
+                offset: self.position.offset + end.offset,
+                ..end
+            };
+        }
+    }
+}
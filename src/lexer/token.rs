@@ -1,61 +1,161 @@
+use std::fmt::Display;
+
 use crate::parser::Expression;
 
-#[derive(Debug, Default)]
+use super::{FileId, Position};
+
+/// The location of a token in the source code. `start` and `len` are byte offsets into the
+/// whole source, while `line` and `column` point at the first character of the token, and
+/// `end_line` and `end_column` right after its last one, with columns being display columns
+/// (see [`Position`]). `file` tells which source of a [`SourceMap`](super::SourceMap) the
+/// token came from.
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct TokenSpan {
+    pub file: FileId,
     pub line: usize,
+    pub column: usize,
     pub start: usize,
     pub len: usize,
+    pub end_line: usize,
+    pub end_column: usize,
 }
 
 impl TokenSpan {
-    pub fn new(line: usize, start: usize, len: usize) -> Self {
-        TokenSpan { line, start, len }
+    /// The span between two positions of the same file.
+    pub fn new(start: &Position, end: &Position) -> Self {
+        TokenSpan {
+            file: start.file,
+            line: start.line,
+            column: start.column,
+            start: start.offset,
+            len: end.offset - start.offset,
+            end_line: end.line,
+            end_column: end.column,
+        }
+    }
+
+    /// The span from the start of this span to the end of `end`.
+    pub fn to(&self, end: &TokenSpan) -> TokenSpan {
+        TokenSpan {
+            len: (end.start + end.len).saturating_sub(self.start),
+            end_line: end.end_line,
+            end_column: end.end_column,
+            ..self.clone()
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TokenWrapper {
     pub token: Token,
     pub span: TokenSpan,
+    /// Only kept by [`Lexer::tokenize_lossless`](super::Lexer::tokenize_lossless)
+    pub trivia: Option<Box<Trivia>>,
 }
 
 impl TokenWrapper {
-    pub fn len(&self) -> usize {
-        self.span.len
+    pub fn new(token: Token, span: TokenSpan) -> Self {
+        TokenWrapper {
+            token,
+            span,
+            trivia: None,
+        }
     }
 }
 
+/// The text of a token exactly as it was written, along with the whitespace and comments around
+/// it. Trailing trivia are the ones found on the same line as the token, while the rest belong
+/// to the leading trivia of the next token.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Trivia {
+    pub text: String,
+    pub leading: Vec<TriviaPiece>,
+    pub trailing: Vec<TriviaPiece>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriviaPiece {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: TokenSpan,
+}
+
+/// A piece of a string template: either plain text, or the tokens of an embedded `${...}`
+/// expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateFragment {
+    Text(String),
+    Expression(Vec<TokenWrapper>),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Keyword {
     Let,
     If,
+    Elif,
+    Else,
     Fun,
     End,
     While,
+    For,
+    In,
+    Break,
+    Continue,
     True,
     False,
     Return,
 }
 
 impl Keyword {
-    pub fn all<'a>() -> Vec<&'a str> {
-        vec![
-            "let", "if", "fun", "end", "while", "true", "false", "return",
-        ]
-    }
-
-    pub fn from_string(string: &str) -> Keyword {
-        match string {
+    pub fn from_string(string: &str) -> Option<Keyword> {
+        let keyword = match string {
             "let" => Keyword::Let,
             "if" => Keyword::If,
+            "elif" => Keyword::Elif,
+            "else" => Keyword::Else,
             "fun" => Keyword::Fun,
             "end" => Keyword::End,
             "while" => Keyword::While,
+            "for" => Keyword::For,
+            "in" => Keyword::In,
+            "break" => Keyword::Break,
+            "continue" => Keyword::Continue,
             "true" => Keyword::True,
             "false" => Keyword::False,
             "return" => Keyword::Return,
-            _ => todo!("unable to recognize keyword {:?}", string),
-        }
+            _ => return None,
+        };
+
+        Some(keyword)
+    }
+}
+
+impl Display for Keyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keyword = match self {
+            Keyword::Let => "let",
+            Keyword::If => "if",
+            Keyword::Elif => "elif",
+            Keyword::Else => "else",
+            Keyword::Fun => "fun",
+            Keyword::End => "end",
+            Keyword::While => "while",
+            Keyword::For => "for",
+            Keyword::In => "in",
+            Keyword::Break => "break",
+            Keyword::Continue => "continue",
+            Keyword::True => "true",
+            Keyword::False => "false",
+            Keyword::Return => "return",
+        };
+
+        write!(f, "{}", keyword)
     }
 }
 
@@ -66,6 +166,7 @@ pub enum Token {
     Identifier(String),
     NumberLiteral(f64),
     StringLiteral(String),
+    Template(Vec<TemplateFragment>),
     BooleanLiteral(bool),
     OpenBracket,
     CloseBracket,
@@ -78,50 +179,77 @@ pub enum Token {
     Equals,
     Comma,
     Comment(String),
+    /// A `##` comment, without the `##` and the space after it
+    DocComment(String),
+    /// Only emitted by [`Lexer::tokenize_lossless`](super::Lexer::tokenize_lossless), to hold
+    /// the trivia at the end of the source
+    EndOfFile,
     Eq,
     Lte,
     Gte,
     Gt,
     Lt,
     Dot,
+    DotDot,
+    DotDotEq,
     And,
     Or,
     Exclamation,
+    Caret,
+    /// Emitted by the custom tokenizers of a [`LexerBuilder`](super::LexerBuilder), which pick
+    /// the `kind` that tells their tokens apart. The parser reads them through
+    /// [`Parser::extension`](crate::parser::Parser::extension).
+    Custom {
+        kind: &'static str,
+        text: String,
+    },
 }
 
-impl Token {
-    pub fn symbols<'a>() -> Vec<&'a str> {
-        vec![
-            "==", ">=", "<=", "<", ">", "=", "+", "-", "*", "/", "(", ")", ",", "[", "]",
-            "&&", "||", "!"
-        ]
-    }
+/// Writes the token the way it is written in the source code, or describes it when it has no
+/// fixed text.
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Token::Keyword(keyword) => return write!(f, "{}", keyword),
+            Token::Identifier(name) => name,
+            Token::NumberLiteral(value) => return write!(f, "{}", value),
+            Token::StringLiteral(value) => return write!(f, "{:?}", value),
+            Token::BooleanLiteral(value) => return write!(f, "{}", value),
+            Token::Custom { text, .. } => text,
+            Token::Whitespace => "whitespace",
+            Token::Template(_) => "string template",
+            Token::Comment(_) => "comment",
+            Token::DocComment(_) => "doc comment",
+            Token::EndOfFile => "end of file",
+            Token::OpenBracket => "[",
+            Token::CloseBracket => "]",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::OpenParens => "(",
+            Token::CloseParens => ")",
+            Token::Equals => "=",
+            Token::Comma => ",",
+            Token::Eq => "==",
+            Token::Lte => "<=",
+            Token::Gte => ">=",
+            Token::Gt => ">",
+            Token::Lt => "<",
+            Token::Dot => ".",
+            Token::DotDot => "..",
+            Token::DotDotEq => "..=",
+            Token::And => "&&",
+            Token::Or => "||",
+            Token::Exclamation => "!",
+            Token::Caret => "^",
+        };
 
-    pub fn from_symbol(symbol: &str) -> Token {
-        match symbol {
-            "==" => Token::Eq,
-            ">=" => Token::Gte,
-            "<=" => Token::Lte,
-            "<" => Token::Lt,
-            ">" => Token::Gt,
-            "+" => Token::Plus,
-            "-" => Token::Minus,
-            "*" => Token::Star,
-            "/" => Token::Slash,
-            "(" => Token::OpenParens,
-            ")" => Token::CloseParens,
-            "[" => Token::OpenBracket,
-            "]" => Token::CloseBracket,
-            "=" => Token::Equals,
-            "," => Token::Comma,
-            "." => Token::Dot,
-            "&&" => Token::And,
-            "||" => Token::Or,
-            "!" => Token::Exclamation,
-            _ => unreachable!(),
-        }
+        write!(f, "{}", text)
     }
+}
 
+impl Token {
     pub fn value(self) -> Expression {
         match self {
             Token::NumberLiteral(v) => Expression::Number(v),
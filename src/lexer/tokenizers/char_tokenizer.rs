@@ -1,33 +1,39 @@
-use crate::lexer::{TokenSpan, Token};
+use crate::lexer::{Cursor, LexError, Token};
 
-use super::{Position, TokenWrapper, Tokenizer};
+use super::Tokenizer;
 
-pub struct CharTokenizer<'a> {
-    symbols: Vec<&'a str>,
-}
-
-impl CharTokenizer<'_> {
-    pub fn new() -> Self {
-        Self {
-            symbols: Token::symbols(),
-        }
-    }
-}
+pub struct CharTokenizer;
 
-impl Tokenizer for CharTokenizer<'_> {
-    fn tokenize(&self, contents: &str, position: &Position) -> Option<TokenWrapper> {
-        let view = &contents[position.offset..];
-        for &symbol in self.symbols.iter() {
-            if !view.starts_with(symbol) {
-                continue;
-            }
+impl Tokenizer for CharTokenizer {
+    fn tokenize(&self, cursor: &mut Cursor, _errors: &mut Vec<LexError>) -> Option<Token> {
+        let (token, len) = match (cursor.peek()?, cursor.peek_second()) {
+            ('=', Some('=')) => (Token::Eq, 2),
+            ('>', Some('=')) => (Token::Gte, 2),
+            ('<', Some('=')) => (Token::Lte, 2),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('.', Some('.')) if cursor.rest().starts_with("..=") => (Token::DotDotEq, 3),
+            ('.', Some('.')) => (Token::DotDot, 2),
+            ('.', _) => (Token::Dot, 1),
+            ('<', _) => (Token::Lt, 1),
+            ('>', _) => (Token::Gt, 1),
+            ('=', _) => (Token::Equals, 1),
+            ('+', _) => (Token::Plus, 1),
+            ('-', _) => (Token::Minus, 1),
+            ('*', _) => (Token::Star, 1),
+            ('/', _) => (Token::Slash, 1),
+            ('^', _) => (Token::Caret, 1),
+            ('(', _) => (Token::OpenParens, 1),
+            (')', _) => (Token::CloseParens, 1),
+            (',', _) => (Token::Comma, 1),
+            ('[', _) => (Token::OpenBracket, 1),
+            (']', _) => (Token::CloseBracket, 1),
+            ('!', _) => (Token::Exclamation, 1),
+            _ => return None,
+        };
 
-            return Some(TokenWrapper {
-                token: Token::from_symbol(symbol),
-                span: TokenSpan::new(position.line, position.offset, symbol.len()),
-            });
-        }
+        cursor.advance(len);
 
-        None
+        Some(token)
     }
 }
@@ -1,29 +1,67 @@
-use regex::Regex;
+use crate::lexer::{Cursor, LexError, LexErrorKind, Token};
 
-use crate::lexer::{Token, TokenSpan};
+use super::Tokenizer;
 
-use super::{TokenWrapper, Tokenizer, Position, consume_regex};
-
-pub struct CommentTokenizer {
-    regex: Regex,
-}
+/// Recognizes `#` line comments, `##` doc comments, and `#[ ... ]#` block comments, which may
+/// be nested so that regions containing block comments can be commented out.
+pub struct CommentTokenizer;
 
 impl CommentTokenizer {
-    pub fn new() -> Self {
-        Self {
-            regex: Regex::new(r"^(#.*$)").unwrap(),
+    /// Returns the length of the block comment at the start of `view`, or `None` when it is
+    /// not terminated.
+    fn block_end(view: &str) -> Option<usize> {
+        let mut depth = 0;
+        let mut index = 0;
+
+        while index < view.len() {
+            if view[index..].starts_with("#[") {
+                depth += 1;
+                index += 2;
+            } else if view[index..].starts_with("]#") {
+                depth -= 1;
+                index += 2;
+
+                if depth == 0 {
+                    return Some(index);
+                }
+            } else {
+                index += view[index..].chars().next()?.len_utf8();
+            }
         }
+
+        None
     }
 }
 
 impl Tokenizer for CommentTokenizer {
-    fn tokenize(&self, contents: &str, position: &Position) -> Option<TokenWrapper> {
-        let view = &contents[position.offset..];
-        let (text, length) = consume_regex(view, &self.regex)?;
-
-        Some(TokenWrapper {
-            token: Token::Comment(text),
-            span: TokenSpan::new(position.line, position.offset, length),
-        })
+    fn tokenize(&self, cursor: &mut Cursor, errors: &mut Vec<LexError>) -> Option<Token> {
+        if cursor.peek()? != '#' {
+            return None;
+        }
+
+        let view = cursor.rest();
+
+        if view.starts_with("#[") {
+            let len = Self::block_end(view).unwrap_or_else(|| {
+                errors.push(LexError::new(
+                    LexErrorKind::UnterminatedComment,
+                    cursor.span_at(0, 2),
+                ));
+                view.len()
+            });
+            cursor.advance(len);
+
+            return Some(Token::Comment(view[..len].to_owned()));
+        }
+
+        let text = cursor.eat_while(|c| c != '\n');
+
+        match text.strip_prefix("##") {
+            Some(doc) => {
+                let doc = doc.strip_prefix(' ').unwrap_or(doc);
+                Some(Token::DocComment(doc.trim_end().to_owned()))
+            }
+            None => Some(Token::Comment(text.to_owned())),
+        }
     }
 }
@@ -1,30 +1,41 @@
-use regex::Regex;
+use unicode_xid::UnicodeXID;
 
-use crate::lexer::{TokenSpan, Token};
+use crate::lexer::{Cursor, Keyword, LexError, Token};
 
-use super::{TokenWrapper, Tokenizer, Position, consume_regex};
+use super::Tokenizer;
 
-pub struct IdentifierTokenizer {
-    regex: Regex,
-}
+/// Scans identifiers as well as keywords, so that the longest identifier always wins: `letter`
+/// is an identifier, and not the keyword `let` followed by `ter`.
+///
+/// Identifiers follow Unicode's XID rules, so `όνομα` is a valid identifier. They may also
+/// start with `_`, and contain `$`.
+pub struct IdentifierTokenizer;
 
 impl IdentifierTokenizer {
-    pub fn new() -> Self {
-        Self {
-            regex: Regex::new(r"^([a-zA-Z][a-zA-Z0-9_$]*)").unwrap(),
-        }
+    pub fn is_start(c: char) -> bool {
+        c.is_xid_start() || c == '_'
+    }
+
+    pub fn is_continue(c: char) -> bool {
+        c.is_xid_continue() || c == '$'
     }
 }
 
 impl Tokenizer for IdentifierTokenizer {
-    fn tokenize(&self, contents: &str, position: &Position) -> Option<TokenWrapper> {
-        let view = &contents[position.offset..];
+    fn tokenize(&self, cursor: &mut Cursor, _errors: &mut Vec<LexError>) -> Option<Token> {
+        if !Self::is_start(cursor.peek()?) {
+            return None;
+        }
+
+        let text = cursor.eat_while(Self::is_continue);
 
-        let (text, length) = consume_regex(view, &self.regex)?;
+        let token = match Keyword::from_string(text) {
+            Some(Keyword::True) => Token::BooleanLiteral(true),
+            Some(Keyword::False) => Token::BooleanLiteral(false),
+            Some(keyword) => Token::Keyword(keyword),
+            None => Token::Identifier(text.to_owned()),
+        };
 
-        Some(TokenWrapper {
-            token: Token::Identifier(text),
-            span: TokenSpan::new(position.line, position.offset, length),
-        })
+        Some(token)
     }
 }
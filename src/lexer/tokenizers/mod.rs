@@ -1,7 +1,6 @@
 mod char_tokenizer;
 mod comment_tokenizer;
 mod identifier_tokenizer;
-mod keyword_tokenizer;
 mod number_tokenizer;
 mod string_tokenizer;
 mod whitespace_tokenizer;
@@ -9,28 +8,19 @@ mod whitespace_tokenizer;
 pub use char_tokenizer::CharTokenizer;
 pub use comment_tokenizer::CommentTokenizer;
 pub use identifier_tokenizer::IdentifierTokenizer;
-pub use keyword_tokenizer::KeywordTokenizer;
 pub use number_tokenizer::NumberTokenizer;
-use regex::Regex;
 pub use string_tokenizer::StringTokenizer;
 pub use whitespace_tokenizer::WhitespaceTokenizer;
 
-use super::{lexer::Position, TokenWrapper};
+use super::{Cursor, LexError, Token};
 
 pub trait Tokenizer {
-    fn tokenize(&self, contents: &str, position: &Position) -> Option<TokenWrapper>;
+    /// Scans a single token at the cursor. Returns `None`, without moving the cursor, when the
+    /// input is not recognized. Problems found inside a recognized token are pushed to
+    /// `errors`, while the cursor still moves past the invalid input, so that lexing can
+    /// continue right after it.
+    ///
+    /// A returned token must consume input. Otherwise lexing would never move on, so the token
+    /// is dropped and reported as [`LexErrorKind::EmptyToken`](super::LexErrorKind::EmptyToken).
+    fn tokenize(&self, cursor: &mut Cursor, errors: &mut Vec<LexError>) -> Option<Token>;
 }
-
-fn consume_regex(view: &str, regex: &Regex) -> Option<(String, usize)> {
-    let groups = regex.captures(&view)?;
-
-    let first = groups.get(0)?;
-    let captured = groups.get(1)?;
-
-    let full_captured = first.end() - first.start();
-
-    let text = view[captured.start()..captured.end()].to_owned();
-
-    Some((text, full_captured))
-}
-
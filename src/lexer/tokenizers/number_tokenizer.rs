@@ -1,29 +1,128 @@
-use regex::Regex;
+use crate::lexer::{Cursor, LexError, LexErrorKind, Token};
 
-use crate::lexer::{Token, TokenSpan};
+use super::{IdentifierTokenizer, Tokenizer};
 
-use super::{consume_regex, Position, TokenWrapper, Tokenizer};
-
-pub struct NumberTokenizer {
-    regex: Regex,
-}
+/// Recognizes decimal literals with optional fraction and exponent (`1`, `1.5`, `.5`, `1e-3`),
+/// hexadecimal, binary and octal literals (`0xFF`, `0b1010`, `0o17`), all of which may use `_`
+/// as a separator between digits (`1_000_000`).
+pub struct NumberTokenizer;
 
 impl NumberTokenizer {
-    pub fn new() -> Self {
-        Self {
-            regex: Regex::new(r"^([0-9]+\.?[0-9]*)").unwrap(),
+    /// Returns the length of the literal at the start of `view` and its value, or `None` when
+    /// the literal is malformed.
+    fn scan(view: &str) -> (usize, Option<f64>) {
+        let radix = match view.get(..2) {
+            Some("0x" | "0X") => 16,
+            Some("0b" | "0B") => 2,
+            Some("0o" | "0O") => 8,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            let len = 2 + Self::trailing(&view[2..]);
+            return (len, Self::integer(&view[2..len], radix));
+        }
+
+        let integer = Self::digits(view);
+        let mut len = integer;
+        let mut valid = true;
+
+        if view[len..].starts_with('.') && !view[len..].starts_with("..") {
+            let fraction = Self::digits(&view[len + 1..]);
+            valid &= fraction > 0;
+            len += 1 + fraction;
+        }
+
+        if view[len..].starts_with(['e', 'E']) {
+            len += 1;
+            if view[len..].starts_with(['+', '-']) {
+                len += 1;
+            }
+
+            let exponent = Self::digits(&view[len..]);
+            valid &= exponent > 0;
+            len += exponent;
         }
+
+        // Anything glued to the literal, like in `12abc` or `1.2.3`, is part of the error
+        let trailing = Self::trailing(&view[len..]);
+        valid &= trailing == 0 && Self::separators_are_valid(&view[..len], u8::is_ascii_digit);
+        len += trailing;
+
+        let value = view[..len]
+            .replace('_', "")
+            .parse::<f64>()
+            .ok()
+            .filter(|_| valid);
+
+        (len, value)
+    }
+
+    /// The length of the decimal digits and separators at the start of `view`.
+    fn digits(view: &str) -> usize {
+        view.find(|c: char| !c.is_ascii_digit() && c != '_')
+            .unwrap_or(view.len())
+    }
+
+    /// The length of the identifier-like characters at the start of `view`.
+    fn trailing(view: &str) -> usize {
+        let mut len = 0;
+        for (i, c) in view.char_indices() {
+            let glued_dot = c == '.' && view[i + 1..].starts_with(IdentifierTokenizer::is_continue);
+            if !IdentifierTokenizer::is_continue(c) && !glued_dot {
+                break;
+            }
+            len = i + c.len_utf8();
+        }
+
+        len
+    }
+
+    fn integer(digits: &str, radix: u32) -> Option<f64> {
+        if digits.is_empty() || !Self::separators_are_valid(digits, u8::is_ascii_alphanumeric) {
+            return None;
+        }
+
+        digits
+            .chars()
+            .filter(|&c| c != '_')
+            .try_fold(0.0, |value, c| {
+                Some(value * radix as f64 + c.to_digit(radix)? as f64)
+            })
+    }
+
+    /// A separator is only allowed between two digits.
+    fn separators_are_valid(literal: &str, is_digit: fn(&u8) -> bool) -> bool {
+        let bytes = literal.as_bytes();
+
+        bytes.iter().enumerate().all(|(i, &b)| {
+            b != b'_'
+                || (i > 0 && is_digit(&bytes[i - 1]) && bytes.get(i + 1).is_some_and(is_digit))
+        })
     }
 }
 
 impl Tokenizer for NumberTokenizer {
-    fn tokenize(&self, contents: &str, position: &Position) -> Option<TokenWrapper> {
-        let view = &contents[position.offset..];
-        let (text, length) = consume_regex(view, &self.regex)?;
+    fn tokenize(&self, cursor: &mut Cursor, errors: &mut Vec<LexError>) -> Option<Token> {
+        match (cursor.peek()?, cursor.peek_second()) {
+            (c, _) if c.is_ascii_digit() => {}
+            ('.', Some(c)) if c.is_ascii_digit() => {}
+            _ => return None,
+        }
 
-        Some(TokenWrapper {
-            token: Token::NumberLiteral(text.parse::<f64>().unwrap()),
-            span: TokenSpan::new(position.line, position.offset, length),
-        })
+        let view = cursor.rest();
+        let (length, value) = Self::scan(view);
+
+        let value = value.unwrap_or_else(|| {
+            errors.push(LexError::new(
+                LexErrorKind::MalformedNumber(view[..length].to_owned()),
+                cursor.span_at(0, length),
+            ));
+            f64::NAN
+        });
+
+        cursor.advance(length);
+
+        Some(Token::NumberLiteral(value))
     }
 }
@@ -1,39 +1,313 @@
-use crate::lexer::{TokenSpan, Token};
+use std::{iter::Peekable, ops::Range, str::CharIndices};
 
-use super::{Position, TokenWrapper, Tokenizer};
+use crate::lexer::{Cursor, LexError, LexErrorKind, Lexer, TemplateFragment, Token};
 
-pub struct StringTokenizer;
+use super::Tokenizer;
 
-impl StringTokenizer {
-    pub fn new() -> Self {
-        Self {}
-    }
+/// Recognizes string literals, and string templates with `${...}` expressions, which are
+/// tokenized by `lexer`, so that they may use the custom tokenizers as well.
+///
+/// Strings in triple quotes (`"""..."""`) may span several lines, and the indentation common to
+/// their lines is stripped. Raw strings (`r"..."` and `r"""..."""`) keep backslashes and `${`
+/// as they are written.
+pub struct StringTokenizer<'a> {
+    pub lexer: &'a Lexer,
 }
 
-impl Tokenizer for StringTokenizer {
-    fn tokenize(&self, contents: &str, position: &Position) -> Option<TokenWrapper> {
-        let view = &contents[position.offset..];
+impl StringTokenizer<'_> {
+    /// Decodes the escape sequence that follows a backslash. On failure the byte range of the
+    /// offending characters is returned, relative to the start of the string.
+    fn unescape(view: &str, chars: &mut Peekable<CharIndices>) -> Result<char, Range<usize>> {
+        let Some(&(index, c)) = chars.peek() else {
+            return Err(view.len() - 1..view.len());
+        };
 
-        if !view.starts_with('"') {
-            return None;
+        // A backslash at the end of the line is invalid, but the newline still ends the string
+        if c == '\n' {
+            return Err(index - 1..index);
+        }
+
+        chars.next();
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            '0' => Ok('\0'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '$' => Ok('$'),
+            'x' => {
+                let code = Self::hex_digits(view, chars, 2, 2)?;
+                char::from_u32(code.0).ok_or(code.1)
+            }
+            'u' => {
+                Self::expect(view, chars, '{')?;
+                let code = Self::hex_digits(view, chars, 1, 6)?;
+                Self::expect(view, chars, '}')?;
+
+                char::from_u32(code.0).ok_or(code.1)
+            }
+            _ => Err(index..index + c.len_utf8()),
+        }
+    }
+
+    /// Consumes `expected`, or returns the range of the character found instead. That character
+    /// is left alone, since it may be the closing quote or a line break that ends the string.
+    fn expect(
+        view: &str,
+        chars: &mut Peekable<CharIndices>,
+        expected: char,
+    ) -> Result<(), Range<usize>> {
+        match chars.peek() {
+            Some(&(_, c)) if c == expected => {
+                chars.next();
+                Ok(())
+            }
+            Some(&(i, '\n')) => Err(i..i),
+            Some(&(i, c)) => Err(i..i + c.len_utf8()),
+            None => Err(view.len()..view.len()),
+        }
+    }
+
+    /// Finds the `}` that closes an interpolation whose expression starts at `start`, skipping
+    /// over nested string literals and balanced braces. Returns `None` at the end of the line.
+    fn interpolation_end(view: &str, start: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut index = start;
+
+        while let Some(c) = view[index..].chars().next() {
+            match c {
+                '\n' => return None,
+                '"' => {
+                    index = Self::string_end(view, index)?;
+                    continue;
+                }
+                '{' => depth += 1,
+                '}' if depth == 0 => return Some(index),
+                '}' => depth -= 1,
+                _ => {}
+            }
+
+            index += c.len_utf8();
+        }
+
+        None
+    }
+
+    /// Finds the end of the (possibly templated) string literal that opens at `start`.
+    fn string_end(view: &str, start: usize) -> Option<usize> {
+        let mut index = start + 1;
+
+        while let Some(c) = view[index..].chars().next() {
+            match c {
+                '\n' => return None,
+                '"' => return Some(index + 1),
+                '\\' => index += 1,
+                '$' if view[index..].starts_with("${") => {
+                    index = Self::interpolation_end(view, index + 2)?;
+                }
+                _ => {}
+            }
+
+            index += view[index..].chars().next()?.len_utf8();
+        }
+
+        None
+    }
+
+    /// Finds the closing quotes of a multi-line string whose contents start at `start`.
+    fn multiline_end(view: &str, start: usize, raw: bool) -> Option<usize> {
+        let mut index = start;
+
+        while let Some(c) = view[index..].chars().next() {
+            match c {
+                '"' if view[index..].starts_with(r#"""""#) => return Some(index),
+                '\\' if !raw => index += 1,
+                '$' if !raw && view[index..].starts_with("${") => {
+                    if let Some(end) = Self::interpolation_end(view, index + 2) {
+                        index = end;
+                    }
+                }
+                _ => {}
+            }
+
+            index += view[index..].chars().next()?.len_utf8();
         }
 
-        // parse string
-        let start = 1;
-        let mut len = 0;
-        for c in view[start..].chars() {
-            if c == '"' {
+        None
+    }
+
+    /// The smallest indentation among the lines of `contents` that are not blank.
+    fn common_indent(contents: &str) -> usize {
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn skip_indent(chars: &mut Peekable<CharIndices>, indent: usize) {
+        for _ in 0..indent {
+            if chars.next_if(|&(_, c)| c == ' ' || c == '\t').is_none() {
                 break;
             }
-            len += 1
         }
+    }
+
+    /// Reads between `min` and `max` hexadecimal digits, returning their value and range.
+    fn hex_digits(
+        view: &str,
+        chars: &mut Peekable<CharIndices>,
+        min: usize,
+        max: usize,
+    ) -> Result<(u32, Range<usize>), Range<usize>> {
+        let start = chars.peek().map(|&(i, _)| i).unwrap_or(view.len());
+        let mut value = 0;
+        let mut count = 0;
+
+        while count < max {
+            match chars.peek() {
+                Some(&(_, c)) if c.is_ascii_hexdigit() => {
+                    value = value * 16 + c.to_digit(16).unwrap();
+                    count += 1;
+                    chars.next();
+                }
+                Some(&(i, c)) if count < min => return Err(i..i + c.len_utf8()),
+                None if count < min => return Err(start..start),
+                _ => break,
+            }
+        }
+
+        Ok((value, start..start + count))
+    }
+}
+
+impl Tokenizer for StringTokenizer<'_> {
+    fn tokenize(&self, cursor: &mut Cursor, errors: &mut Vec<LexError>) -> Option<Token> {
+        let view = cursor.rest();
+        let raw = view.starts_with("r\"");
+        let quote = usize::from(raw);
+
+        if !view[quote..].starts_with('"') {
+            return None;
+        }
+
+        // The end of a multi-line string has to be known upfront, since the indentation to
+        // strip depends on all of its lines
+        let multiline = view[quote..].starts_with(r#"""""#);
+        let open = quote + if multiline { 3 } else { 1 };
+        let close = multiline
+            .then(|| Self::multiline_end(view, open, raw))
+            .flatten();
+        let end = close.unwrap_or(view.len());
+        let indent = if multiline {
+            Self::common_indent(&view[open..end])
+        } else {
+            0
+        };
+
+        let mut fragments = vec![];
+        let mut literal = String::new();
+        let mut chars = view[..end].char_indices().peekable();
+        chars.nth(open - 1); // the opening quotes
+        if multiline {
+            Self::skip_indent(&mut chars, indent);
+        }
+
+        let len = loop {
+            let Some(&(index, c)) = chars.peek() else {
+                break close.map(|close| close + 3);
+            };
+
+            match c {
+                '"' if !multiline => break Some(index + 1),
+                '\n' if !multiline => break None,
+                // `\r\n` is a line break like `\n`, which only the `\n` stands for
+                '\r' if multiline && view[index..end].starts_with("\r\n") => {
+                    chars.next();
+                }
+                '\n' => {
+                    chars.next();
+                    let line_end = index - usize::from(view[..index].ends_with('\r'));
+
+                    // Line breaks right after the opening quotes, or right before the closing
+                    // ones, are not part of the string
+                    if view[index + 1..end].trim_matches([' ', '\t']).is_empty() {
+                        while chars.next().is_some() {}
+                    } else if line_end > open {
+                        literal.push('\n');
+                    }
+                    Self::skip_indent(&mut chars, indent);
+                }
+                '\\' if !raw => {
+                    chars.next();
+                    match Self::unescape(&view[..end], &mut chars) {
+                        Ok(c) => literal.push(c),
+                        Err(range) => errors.push(LexError::new(
+                            LexErrorKind::InvalidEscape,
+                            cursor.span_at(range.start, range.len()),
+                        )),
+                    }
+                }
+                '$' if !raw && view[index..].starts_with("${") => {
+                    let Some(end) = Self::interpolation_end(&view[..end], index + 2) else {
+                        let len = view[index..end].find('\n').unwrap_or(end - index);
+                        errors.push(LexError::new(
+                            LexErrorKind::UnterminatedInterpolation,
+                            cursor.span_at(index, len),
+                        ));
+
+                        // Multi-line strings go on after the line with the error
+                        if !multiline {
+                            break Some(index + len);
+                        }
+                        while chars.next_if(|&(i, _)| i < index + len).is_some() {}
+                        continue;
+                    };
 
-        let end = start + len;
-        let literal = view[start..end].to_owned();
+                    let start = cursor.position_at(index + 2);
+                    let contents = &cursor.contents()[..cursor.position().offset + end];
+                    let tokens = self.lexer.tokenize_range(contents, start, errors);
 
-        return Some(TokenWrapper {
-            span: TokenSpan::new(position.line, start, literal.len() + 2),
-            token: Token::StringLiteral(literal),
+                    if !literal.is_empty() {
+                        fragments.push(TemplateFragment::Text(std::mem::take(&mut literal)));
+                    }
+                    fragments.push(TemplateFragment::Expression(tokens));
+
+                    while chars.next_if(|&(i, _)| i <= end).is_some() {}
+                }
+                c => {
+                    chars.next();
+                    literal.push(c);
+                }
+            }
+        };
+
+        let len = len.unwrap_or_else(|| {
+            // Consume the rest of the line, or of the source for multi-line strings, so that
+            // lexing continues after it
+            let len = match multiline {
+                true => view.len(),
+                false => view.find('\n').unwrap_or(view.len()),
+            };
+            errors.push(LexError::new(
+                LexErrorKind::UnterminatedString,
+                cursor.span_at(0, if multiline { open } else { len }),
+            ));
+            len
         });
+
+        let token = if fragments.is_empty() {
+            Token::StringLiteral(literal)
+        } else {
+            if !literal.is_empty() {
+                fragments.push(TemplateFragment::Text(literal));
+            }
+            Token::Template(fragments)
+        };
+
+        cursor.advance(len);
+
+        Some(token)
     }
 }
@@ -1,30 +1,17 @@
-use regex::Regex;
+use crate::lexer::{Cursor, LexError, Token};
 
-use crate::lexer::{Token, TokenSpan, TokenWrapper};
+use super::Tokenizer;
 
-use super::{consume_regex, Position, Tokenizer};
-
-pub struct WhitespaceTokenizer {
-    regex: Regex,
-}
-
-impl WhitespaceTokenizer {
-    pub fn new() -> Self {
-        Self {
-            regex: Regex::new(r"^(\s+)").unwrap(),
-        }
-    }
-}
+pub struct WhitespaceTokenizer;
 
 impl Tokenizer for WhitespaceTokenizer {
-    fn tokenize(&self, contents: &str, position: &Position) -> Option<TokenWrapper> {
-        let view = &contents[position.offset..];
+    fn tokenize(&self, cursor: &mut Cursor, _errors: &mut Vec<LexError>) -> Option<Token> {
+        if !cursor.peek()?.is_whitespace() {
+            return None;
+        }
 
-        let (_, length) = consume_regex(view, &self.regex)?;
+        cursor.eat_while(char::is_whitespace);
 
-        Some(TokenWrapper {
-            token: Token::Whitespace,
-            span: TokenSpan::new(position.line, position.offset, length),
-        })
+        Some(Token::Whitespace)
     }
 }
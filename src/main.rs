@@ -1,29 +1,57 @@
 use std::path::Path;
 
-mod lexer;
-mod parser;
-mod runtime;
-
-use lexer::Lexer;
-use parser::Parser;
-use runtime::Runtime;
+use toylang::{
+    lexer::{Lexer, SourceMap},
+    parser::Parser,
+    runtime::Runtime,
+};
 
 fn main() {
-    let argument = std::env::args().nth(1).expect("Please provide a filename");
-    let filepath = Path::new(&argument);
+    let argument = std::env::args()
+        .nth(1)
+        .expect("Please provide a filename, or - to read the program from stdin");
+
+    let mut sources = SourceMap::new();
+    let mut parser = if argument == "-" {
+        // Piped programs are parsed while they are still being read
+        Parser::from_stream(Lexer::new().stream(std::io::stdin().lock()))
+    } else {
+        let filepath = Path::new(&argument);
+
+        if !filepath.exists() {
+            panic!("File not found: {}", argument);
+        }
+
+        let contents = std::fs::read_to_string(filepath).expect("Unable to read file");
+        let file = sources.add(argument.as_str(), contents);
+
+        match Lexer::new().tokenize_file(&sources, file) {
+            Ok(tokens) => Parser::new(tokens),
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{}", sources.render(&error.span, &error.kind));
+                }
 
-    if !filepath.exists() {
-        panic!("File not found: {}", argument);
-    }
+                std::process::exit(1);
+            }
+        }
+    };
 
-    let contents = std::fs::read_to_string(filepath)
-        .expect("Unable to read file")
-        .to_string();
+    let ast = match parser.parse() {
+        Ok(ast) => ast,
+        Err(errors) => {
+            for error in errors {
+                if argument == "-" {
+                    let (line, column) = (error.span.line + 1, error.span.column + 1);
+                    eprintln!("{}:{}:{}: {}", argument, line, column, error);
+                } else {
+                    eprintln!("{}", sources.render(&error.span, &error));
+                }
+            }
 
-    let tokens = Lexer::tokenize(contents);
-    let ast = Parser::new(tokens)
-        .parse()
-        .expect("Unable to parse program!");
+            std::process::exit(1);
+        }
+    };
 
     Runtime::new().run(ast);
 }
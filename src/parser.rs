@@ -1,104 +1,433 @@
-mod bool_expression;
+mod error;
 mod expression;
+mod operator_expression;
+mod statement;
 
-use crate::lexer::token::*;
-use bool_expression::BoolExpressionParser;
-pub use expression::*;
+use std::{
+    collections::{HashMap, VecDeque},
+    iter::Peekable,
+    rc::Rc,
+};
 
-pub struct Parser {
-    tokens: Vec<TokenWrapper>,
-    current: usize,
+use crate::lexer::{token::*, LexError};
+pub use error::*;
+pub use expression::*;
+use operator_expression::{OperatorExpressionParser, Precedence};
+pub use statement::*;
+
+/// Parses tokens as they come from a token source, like a
+/// [`TokenStream`](crate::lexer::TokenStream), looking at most two tokens ahead.
+pub struct Parser<'a> {
+    /// The tokens that were read from `source` but not consumed yet
+    tokens: VecDeque<TokenWrapper>,
+    /// Doc comments waiting to find out whether a declaration follows them
+    docs: Vec<TokenWrapper>,
+    source: Peekable<Box<dyn Iterator<Item = Result<TokenWrapper, LexError>> + 'a>>,
+    lex_error: Option<LexError>,
+    /// How many loops the statement being parsed is in, within the innermost function
+    loops: usize,
+    /// How many blocks the statement being parsed is in
+    blocks: usize,
+    /// How many tokens were consumed so far
+    consumed: usize,
+    /// The span of the last token that was consumed
+    previous: Option<TokenSpan>,
+    /// The constructs the expression being parsed is in, innermost last
+    contexts: Vec<Context>,
+    /// The errors that were recovered from
+    errors: Vec<ParseError>,
+    /// What the custom tokens of each kind are parsed as
+    extensions: HashMap<&'static str, Extension<'a>>,
 }
 
-#[derive(Debug)]
-pub enum ParseError {
-    InvalidToken(Token),
-    EndOfInput,
+/// Errors are boxed, since they are much larger than most of what is parsed.
+pub type ParseResult<T> = Result<T, Box<ParseError>>;
+
+/// Makes an operand out of the text of a custom token, see [`Parser::extension`].
+type Extension<'a> = Rc<dyn Fn(&str) -> Expression + 'a>;
+
+impl Parser<'static> {
+    pub fn new(tokens: Vec<TokenWrapper>) -> Self {
+        Parser::from_stream(tokens.into_iter().map(Ok))
+    }
 }
 
-pub type ParseResult<T> = Result<T, ParseError>;
+impl<'a> Parser<'a> {
+    /// Creates a parser that reads the tokens lazily. Parsing stops at the first lexical error.
+    pub fn from_stream(source: impl Iterator<Item = Result<TokenWrapper, LexError>> + 'a) -> Self {
+        let mut parser = Parser {
+            tokens: VecDeque::new(),
+            docs: vec![],
+            source: (Box::new(source) as Box<dyn Iterator<Item = _>>).peekable(),
+            lex_error: None,
+            loops: 0,
+            blocks: 0,
+            consumed: 0,
+            previous: None,
+            contexts: vec![],
+            errors: vec![],
+            extensions: HashMap::new(),
+        };
+        parser.fill();
+
+        parser
+    }
+
+    /// Parses the [`Token::Custom`] tokens of `kind` as operands, which `parse` makes out of
+    /// their text. Custom tokens no extension handles are syntax errors.
+    pub fn extension(
+        mut self,
+        kind: &'static str,
+        parse: impl Fn(&str) -> Expression + 'a,
+    ) -> Self {
+        self.extensions.insert(kind, Rc::new(parse));
+        self
+    }
+
+    /// Reads tokens until the current and the next one are known, skipping comments, and the
+    /// doc comments that are not in front of a declaration.
+    fn fill(&mut self) {
+        while self.tokens.len() < 2 && self.lex_error.is_none() {
+            let token = match self.source.next() {
+                Some(Ok(token)) => token,
+                Some(Err(error)) => {
+                    self.lex_error = Some(error);
+                    break;
+                }
+                None => break,
+            };
 
-impl Parser {
-    pub fn new(tokens: Vec<TokenWrapper>) -> Parser {
-        Parser { tokens, current: 0 }
+            match token.token {
+                Token::Comment(_) | Token::EndOfFile => {}
+                Token::DocComment(_) => self.docs.push(token),
+                Token::Keyword(Keyword::Let) => {
+                    self.tokens.extend(self.docs.drain(..));
+                    self.tokens.push_back(token);
+                }
+                Token::Keyword(Keyword::Fun) => {
+                    // Only a named function is a declaration, `fun(` starts an anonymous one
+                    match self.source.peek() {
+                        Some(Ok(TokenWrapper {
+                            token: Token::Identifier(_),
+                            ..
+                        })) => self.tokens.extend(self.docs.drain(..)),
+                        _ => self.docs.clear(),
+                    }
+                    self.tokens.push_back(token);
+                }
+                _ => {
+                    self.docs.clear();
+                    self.tokens.push_back(token);
+                }
+            }
+        }
     }
 
     fn current_token(&self) -> ParseResult<Token> {
-        self.tokens
-            .get(self.current)
-            .map(|t| t.token.clone())
-            .ok_or(ParseError::EndOfInput)
+        match self.tokens.front() {
+            Some(token) => Ok(token.token.clone()),
+            None => Err(self.error(vec![])),
+        }
+    }
+
+    /// An error at the current token, where one of `expected` should have been.
+    fn error(&self, expected: Vec<Expected>) -> Box<ParseError> {
+        let (kind, span) = match (self.tokens.front(), &self.lex_error) {
+            (Some(token), _) => (
+                ParseErrorKind::InvalidToken(token.token.clone()),
+                token.span.clone(),
+            ),
+            (None, Some(error)) => (ParseErrorKind::Lex(error.clone()), error.span.clone()),
+            (None, None) => (ParseErrorKind::EndOfInput, self.end_span()),
+        };
+
+        Box::new(ParseError {
+            kind,
+            span,
+            expected,
+            context: self.contexts.last().cloned(),
+        })
+    }
+
+    /// The empty span right after the last token.
+    fn end_span(&self) -> TokenSpan {
+        match &self.previous {
+            Some(span) => TokenSpan {
+                start: span.start + span.len,
+                line: span.end_line,
+                column: span.end_column,
+                len: 0,
+                ..span.clone()
+            },
+            None => TokenSpan::default(),
+        }
+    }
+
+    /// Parses a construct starting at the current token, so that the errors found in it can
+    /// tell where it was opened.
+    fn in_context<T>(
+        &mut self,
+        construct: Construct,
+        parse: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        let span = self
+            .tokens
+            .front()
+            .map(|t| t.span.clone())
+            .unwrap_or_default();
+        self.contexts.push(Context { construct, span });
+        let result = parse(self);
+        self.contexts.pop();
+
+        result
     }
 
     fn peek(&self) -> Option<Token> {
-        self.tokens.get(self.current + 1).map(|t| t.token.clone())
+        self.tokens.get(1).map(|t| t.token.clone())
     }
 
     fn parse_if_statement(&mut self) -> ParseResult<Expression> {
         self.try_consume(Token::Keyword(Keyword::If))?;
 
-        let condition = Box::new(self.parse_expression()?);
+        self.parse_conditional()
+    }
+
+    /// Parses the condition and the branches following an `if` or an `elif`, up to and
+    /// including the `end` they all share.
+    fn parse_conditional(&mut self) -> ParseResult<Expression> {
+        let condition = self.parse_expression()?;
+        let body = self.parse_block(&[Keyword::End, Keyword::Elif, Keyword::Else])?;
+
+        let alternative = match self.current_token()? {
+            Token::Keyword(Keyword::Elif) => {
+                let start = self.start_span();
+                self.consume();
+                let conditional = self.parse_conditional()?;
+                let conditional = Statement::Expression(self.node_from(conditional, &start));
+
+                Some(vec![self.spanned(conditional, &start)])
+            }
+            Token::Keyword(Keyword::Else) => {
+                self.consume();
+                Some(self.parse_body()?)
+            }
+            _ => {
+                self.try_consume(Token::Keyword(Keyword::End))?;
+                None
+            }
+        };
+
+        Ok(Expression::IfCondition {
+            condition,
+            body,
+            alternative,
+        })
+    }
+
+    /// Parses the statements of a block, up to one of the keywords that close it.
+    fn parse_block(&mut self, terminators: &[Keyword]) -> ParseResult<AST> {
+        self.blocks += 1;
+        let body = self.parse_block_items(terminators);
+        self.blocks -= 1;
 
+        body
+    }
+
+    fn parse_block_items(&mut self, terminators: &[Keyword]) -> ParseResult<AST> {
         let mut body: AST = Vec::new();
         loop {
-            if let Ok(Token::Keyword(Keyword::End)) = self.current_token() {
-                self.try_consume(Token::Keyword(Keyword::End))?;
-                break;
+            match self.current_token() {
+                Ok(Token::Keyword(keyword)) if terminators.contains(&keyword) => break,
+                Ok(_) => body.push(self.parse_item()),
+                Err(_) => {
+                    let expected = terminators
+                        .iter()
+                        .map(|keyword| Expected::Token(Token::Keyword(keyword.clone())))
+                        .collect();
+
+                    return Err(self.error(expected));
+                }
             }
+        }
+
+        Ok(body)
+    }
+
+    /// Parses a block up to and including its `end`.
+    fn parse_body(&mut self) -> ParseResult<AST> {
+        let body = self.parse_block(&[Keyword::End])?;
+        self.try_consume(Token::Keyword(Keyword::End))?;
+
+        Ok(body)
+    }
+
+    /// Parses a statement of a block or of the program. When it cannot be parsed, the error is
+    /// recorded and the tokens up to the start of the next statement are skipped.
+    fn parse_item(&mut self) -> Spanned<Statement> {
+        let start = self.consumed;
+        let start_span = self.start_span();
+
+        match self.parse_statement() {
+            Ok(statement) => self.spanned(statement, &start_span),
+            Err(error) => {
+                // A lexical error ends the token source, so it is only reported once
+                if !matches!(error.kind, ParseErrorKind::Lex(_)) {
+                    self.errors.push(*error);
+                }
+
+                // The token the error was found at might be a statement start itself
+                if self.consumed == start {
+                    self.consume();
+                }
+                self.synchronize();
 
-            let expr = Box::new(self.parse_expression()?);
-            body.push(expr);
+                self.spanned(Statement::Error, &start_span)
+            }
         }
+    }
 
-        Ok(Expression::IfCondition { condition, body })
+    /// Skips tokens until one that a statement starts with, or one that closes the enclosing
+    /// block.
+    fn synchronize(&mut self) {
+        while let Ok(token) = self.current_token() {
+            match token {
+                Token::Keyword(
+                    Keyword::Let
+                    | Keyword::Fun
+                    | Keyword::If
+                    | Keyword::While
+                    | Keyword::For
+                    | Keyword::Return
+                    | Keyword::Break
+                    | Keyword::Continue,
+                )
+                | Token::DocComment(_) => break,
+                Token::Keyword(Keyword::End | Keyword::Elif | Keyword::Else) if self.blocks > 0 => {
+                    break
+                }
+                _ => self.consume(),
+            }
+        }
     }
 
-    fn parse_expression(&mut self) -> ParseResult<Expression> {
-        let token = self.current_token()?;
+    fn parse_statement(&mut self) -> ParseResult<Statement> {
+        match self.current_token()? {
+            Token::Keyword(Keyword::Let) => self.parse_variable_decleration(),
+            Token::Keyword(Keyword::Fun) if matches!(self.peek(), Some(Token::Identifier(_))) => {
+                self.in_context(Construct::Function, Self::parse_function_decleration)
+            }
+            Token::Keyword(Keyword::While) => {
+                self.in_context(Construct::While, Self::parse_while_loop)
+            }
+            Token::Keyword(Keyword::For) => self.in_context(Construct::For, Self::parse_for_loop),
+            Token::Keyword(Keyword::Return) => self.parse_return_statement(),
+            Token::Keyword(keyword @ (Keyword::Break | Keyword::Continue)) => {
+                if self.loops == 0 {
+                    let mut error = self.error(vec![]);
+                    error.kind = ParseErrorKind::OutsideLoop(keyword);
 
-        let parsed = match token {
-            Token::Exclamation => {
+                    return Err(error);
+                }
                 self.consume();
-                let expr = self.parse_expression()?;
 
-                // This really needs to factor in operator precedence
-                Ok(Expression::BoolNegation(Box::new(expr)))
+                match keyword {
+                    Keyword::Break => Ok(Statement::Break),
+                    _ => Ok(Statement::Continue),
+                }
+            }
+            Token::DocComment(_) => self.parse_documented(),
+            _ => Ok(Statement::Expression(self.parse_expression()?)),
+        }
+    }
+
+    fn parse_expression(&mut self) -> ParseResult<Node> {
+        self.parse_binary_expression(Precedence::Lowest)
+    }
+
+    /// The span of the current token, where the next node starts.
+    fn start_span(&self) -> TokenSpan {
+        match self.tokens.front() {
+            Some(token) => token.span.clone(),
+            None => self.end_span(),
+        }
+    }
+
+    /// The span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: &TokenSpan) -> TokenSpan {
+        start.to(self.previous.as_ref().unwrap_or(start))
+    }
+
+    fn spanned<T>(&self, node: T, start: &TokenSpan) -> Spanned<T> {
+        Spanned::new(node, self.span_from(start))
+    }
+
+    /// Makes a node of an expression starting at `start` and ending with the last consumed token.
+    fn node_from(&self, expression: Expression, start: &TokenSpan) -> Node {
+        Box::new(self.spanned(expression, start))
+    }
+
+    /// Parses the expressions that operators apply to.
+    fn parse_operand(&mut self) -> ParseResult<Node> {
+        let Ok(token) = self.current_token() else {
+            return Err(self.error(vec![Expected::Expression]));
+        };
+        let start = self.start_span();
+
+        let expression = match token {
+            Token::Keyword(Keyword::If) => self.in_context(Construct::If, Self::parse_if_statement),
+            Token::Keyword(Keyword::Fun) => {
+                self.in_context(Construct::Function, Self::parse_anonymous_function)
             }
-            Token::Keyword(Keyword::If) => self.parse_if_statement(),
-            Token::Keyword(Keyword::Return) => self.parse_return_statement(),
-            Token::Keyword(Keyword::Let) => self.parse_variable_decleration(),
-            Token::Keyword(Keyword::Fun) => self.parse_function_decleration(),
-            Token::Keyword(Keyword::While) => self.parse_while_loop(),
             Token::BooleanLiteral(_) | Token::StringLiteral(_) | Token::NumberLiteral(_) => {
                 self.consume();
                 Ok(token.value())
             }
-            Token::OpenBracket => self.parse_array(),
+            Token::Template(fragments) => {
+                self.consume();
+                self.parse_template(fragments, &start)
+            }
+            Token::OpenBracket => self.in_context(Construct::Array, Self::parse_array),
+            Token::OpenParens => {
+                let mut node = self.in_context(Construct::Parentheses, |parser| {
+                    parser.consume();
+                    let node = parser.parse_expression()?;
+                    parser.try_consume(Token::CloseParens)?;
+
+                    Ok(node)
+                })?;
+                node.span = self.span_from(&start);
+
+                return self.parse_indexing(node);
+            }
             Token::Identifier(name) => match self.peek() {
                 // TODO: implement fields
                 Some(Token::Equals) => self.parse_variable_assignment(),
-                Some(Token::OpenParens) => self.parse_function_call(),
+                Some(Token::OpenParens) => {
+                    let call = self.in_context(Construct::Call, Self::parse_function_call)?;
+                    return self.parse_indexing(self.node_from(call, &start));
+                }
                 Some(Token::OpenBracket) => {
                     self.consume(); // The identifier
-                    self.try_consume(Token::OpenBracket)?;
-                    let index = self.parse_expression()?;
-                    self.try_consume(Token::CloseBracket)?;
+                    let variable = self.node_from(Expression::Variable(name.to_owned()), &start);
+                    let index = self.parse_index()?;
 
                     if let Ok(Token::Equals) = self.current_token() {
                         self.try_consume(Token::Equals)?;
                         let value = self.parse_expression()?;
 
-                        return Ok(Expression::ArrayAssignment {
+                        Ok(Expression::ArrayAssignment {
                             identifier: name.to_owned(),
-                            index: Box::new(index),
-                            value: Box::new(value),
-                        });
+                            index,
+                            value,
+                        })
+                    } else {
+                        let indexing = Expression::ArrayIndexing {
+                            identifier: variable,
+                            index,
+                        };
+
+                        return self.parse_indexing(self.node_from(indexing, &start));
                     }
-
-                    Ok(Expression::ArrayIndexing {
-                        identifier: Box::new(Expression::Variable(name.to_owned())),
-                        index: Box::new(index),
-                    })
                 }
                 _ => {
                     self.consume();
@@ -106,96 +435,166 @@ impl Parser {
                     Ok(Expression::Variable(name.to_owned()))
                 }
             },
-            _ => Err(ParseError::InvalidToken(token)),
-        }?;
+            Token::Custom { kind, text } => {
+                let Some(parse) = self.extensions.get(kind) else {
+                    let mut error = self.error(vec![]);
+                    error.kind = ParseErrorKind::UnhandledCustom { kind, text };
+
+                    return Err(error);
+                };
+                let expression = parse(&text);
+                self.consume();
 
-        match self.current_token() {
-            Ok(Token::Plus) => self.parse_addition(parsed),
-            Ok(Token::Minus) => self.parse_subtraction(parsed),
-            Ok(Token::Star) => self.parse_multiplication(parsed),
-            Ok(Token::Slash) => self.parse_division(parsed),
-            Ok(Token::Lte | Token::Lt | Token::Gte | Token::Gt | Token::And | Token::Or) => {
-                self.parse_boolean_expression(parsed)
+                Ok(expression)
             }
-            _ => Ok(parsed),
+            _ => Err(self.error(vec![Expected::Expression])),
+        }?;
+
+        Ok(self.node_from(expression, &start))
+    }
+
+    /// Parses an index in brackets.
+    fn parse_index(&mut self) -> ParseResult<Node> {
+        self.in_context(Construct::Indexing, |parser| {
+            parser.try_consume(Token::OpenBracket)?;
+            let index = parser.parse_expression()?;
+            parser.try_consume(Token::CloseBracket)?;
+
+            Ok(index)
+        })
+    }
+
+    /// Parses the indexing that follows `target`, if any, like in `matrix[1][2]`.
+    fn parse_indexing(&mut self, mut target: Node) -> ParseResult<Node> {
+        while let Ok(Token::OpenBracket) = self.current_token() {
+            let start = target.span.clone();
+            let index = self.parse_index()?;
+
+            let indexing = Expression::ArrayIndexing {
+                identifier: target,
+                index,
+            };
+            target = self.node_from(indexing, &start);
         }
+
+        Ok(target)
     }
 
-    pub fn parse(&mut self) -> ParseResult<AST> {
-        // I don't like that i have to do this here, but hey
-        self.tokens.retain(|t| match t.token {
-            Token::Comment(_) => false,
-            _ => true,
-        });
+    /// Parses the whole program, returning every syntax error found in it.
+    pub fn parse(&mut self) -> Result<AST, Vec<ParseError>> {
+        let (ast, errors) = self.parse_with_recovery();
 
-        let mut ast: AST = Vec::new();
-        while let Ok(_) = self.current_token() {
-            let expression = self.parse_expression()?;
-            ast.push(Box::new(expression));
+        if !errors.is_empty() {
+            return Err(errors);
         }
 
         Ok(ast)
     }
 
-    fn identifier_name(&mut self, token: Token) -> ParseResult<String> {
-        match token {
-            Token::Identifier(ref n) => {
+    /// Parses the whole program, going on after syntax errors. The parts that could not be
+    /// parsed are left out of the AST as [`Statement::Error`] nodes.
+    pub fn parse_with_recovery(&mut self) -> (AST, Vec<ParseError>) {
+        let mut ast: AST = Vec::new();
+        while self.current_token().is_ok() {
+            ast.push(self.parse_item());
+        }
+
+        let mut errors = std::mem::take(&mut self.errors);
+        if self.lex_error.is_some() {
+            errors.push(*self.error(vec![]));
+        }
+
+        (ast, errors)
+    }
+
+    fn identifier_name(&mut self) -> ParseResult<String> {
+        match self.current_token() {
+            Ok(Token::Identifier(n)) => {
                 self.consume();
-                Ok(n.to_owned())
+                Ok(n)
             }
-            _ => Err(ParseError::InvalidToken(token)),
+            _ => Err(self.error(vec![Expected::Identifier])),
         }
     }
 
     fn try_consume(&mut self, token: Token) -> ParseResult<()> {
-        let current = self.current_token()?;
-
-        if current == token {
-            self.current += 1;
+        if self.tokens.front().is_some_and(|t| t.token == token) {
+            self.consume();
             return Ok(());
         }
 
-        Err(ParseError::InvalidToken(current))
+        Err(self.error(vec![Expected::Token(token)]))
     }
 
     fn consume(&mut self) {
-        self.current += 1;
+        if let Some(token) = self.tokens.pop_front() {
+            self.consumed += 1;
+            self.previous = Some(token.span);
+        }
+        self.fill();
     }
 
-    fn parse_while_loop(&mut self) -> ParseResult<Expression> {
+    fn parse_while_loop(&mut self) -> ParseResult<Statement> {
         self.try_consume(Token::Keyword(Keyword::While))?;
 
         let condition = self.parse_expression()?;
 
-        let mut body = Vec::new();
-        // TODO: abstract this
-        loop {
-            if let Ok(Token::Keyword(Keyword::End)) = self.current_token() {
-                self.try_consume(Token::Keyword(Keyword::End))?;
-                break;
-            }
+        self.loops += 1;
+        let body = self.parse_body();
+        self.loops -= 1;
+        let body = body?;
+
+        Ok(Statement::WhileLoop { condition, body })
+    }
 
-            let expr = Box::new(self.parse_expression()?);
-            body.push(expr);
+    fn parse_for_loop(&mut self) -> ParseResult<Statement> {
+        self.try_consume(Token::Keyword(Keyword::For))?;
+
+        let mut index = None;
+        let mut item = self.identifier_name()?;
+        if self.try_consume(Token::Comma).is_ok() {
+            index = Some(item);
+            item = self.identifier_name()?;
         }
 
-        Ok(Expression::WhileLoop {
-            condition: Box::new(condition),
+        self.try_consume(Token::Keyword(Keyword::In))?;
+        let iterable = self.parse_expression()?;
+
+        self.loops += 1;
+        let body = self.parse_body();
+        self.loops -= 1;
+        let body = body?;
+
+        Ok(Statement::ForLoop {
+            index,
+            item,
+            iterable,
             body,
         })
     }
 
-    fn parse_function_decleration(&mut self) -> ParseResult<Expression> {
+    fn parse_function_decleration(&mut self) -> ParseResult<Statement> {
         self.try_consume(Token::Keyword(Keyword::Fun))?;
+        let name = self.identifier_name()?;
+        let (arguments, body) = self.parse_function()?;
+
+        Ok(Statement::FunctionDefinition {
+            name,
+            body,
+            arguments,
+            doc: None,
+        })
+    }
 
-        let name_token = self.current_token()?;
+    fn parse_anonymous_function(&mut self) -> ParseResult<Expression> {
+        self.try_consume(Token::Keyword(Keyword::Fun))?;
+        let (arguments, body) = self.parse_function()?;
 
-        let name = match name_token {
-            Token::Identifier(_) => self.identifier_name(name_token),
-            Token::OpenParens => Ok("".to_owned()), // anonymous function
-            _ => Err(ParseError::InvalidToken(name_token.clone())),
-        }?;
+        Ok(Expression::Function { arguments, body })
+    }
 
+    /// Parses the parameters and the body of a function.
+    fn parse_function(&mut self) -> ParseResult<(Vec<String>, AST)> {
         let mut arguments = Vec::new();
         self.try_consume(Token::OpenParens)?;
         while let Ok(Token::Identifier(name)) = self.current_token() {
@@ -208,46 +607,32 @@ impl Parser {
         }
         self.try_consume(Token::CloseParens)?;
 
-        let mut body = Vec::new();
-
-        loop {
-            if let Ok(Token::Keyword(Keyword::End)) = self.current_token() {
-                break;
-            }
-
-            let expr = Box::new(self.parse_expression()?);
-            body.push(expr);
-        }
-
-        self.try_consume(Token::Keyword(Keyword::End))?;
+        // A loop around the definition cannot be left from inside the function
+        let loops = std::mem::take(&mut self.loops);
+        let body = self.parse_body();
+        self.loops = loops;
 
-        Ok(Expression::FunctionDefinition {
-            name,
-            body,
-            arguments,
-        })
+        Ok((arguments, body?))
     }
 
     fn parse_function_call(&mut self) -> ParseResult<Expression> {
-        let current = self.current_token()?;
-
-        let name = self.identifier_name(current)?;
+        let name = self.identifier_name()?;
 
         self.try_consume(Token::OpenParens)?;
 
         let mut arguments = Vec::new();
 
         // no arguments
-        if let Ok(_) = self.try_consume(Token::CloseParens) {
+        if self.try_consume(Token::CloseParens).is_ok() {
             Ok(Expression::FunctionCall { name, arguments })
         } else {
             let argument = self.parse_expression()?;
-            arguments.push(Box::new(argument));
+            arguments.push(argument);
 
             // TODO: make a parse_arguments function
             while self.try_consume(Token::Comma).is_ok() {
                 let argument = self.parse_expression()?;
-                arguments.push(Box::new(argument));
+                arguments.push(argument);
             }
 
             self.try_consume(Token::CloseParens)?;
@@ -256,34 +641,6 @@ impl Parser {
         }
     }
 
-    fn parse_addition(&mut self, left: Expression) -> ParseResult<Expression> {
-        self.try_consume(Token::Plus)?;
-        let right = self.parse_expression()?;
-
-        Ok(Expression::Addition(BinaryExpression::new(left, right)))
-    }
-
-    fn parse_subtraction(&mut self, left: Expression) -> ParseResult<Expression> {
-        self.try_consume(Token::Minus)?;
-        let right = self.parse_expression()?;
-
-        Ok(Expression::Subtraction(BinaryExpression::new(left, right)))
-    }
-
-    fn parse_multiplication(&mut self, left: Expression) -> ParseResult<Expression> {
-        self.try_consume(Token::Star)?;
-        let right = self.parse_expression()?;
-
-        Ok(Expression::Multiplication(BinaryExpression::new(left, right)))
-    }
-
-    fn parse_division(&mut self, left: Expression) -> ParseResult<Expression> {
-        self.try_consume(Token::Slash)?;
-        let right = self.parse_expression()?;
-
-        Ok(Expression::Division(BinaryExpression::new(left, right)))
-    }
-
     fn parse_array(&mut self) -> ParseResult<Expression> {
         self.try_consume(Token::OpenBracket)?;
 
@@ -298,7 +655,7 @@ impl Parser {
 
             let expr = self.parse_expression()?;
 
-            items.push(Box::new(expr));
+            items.push(expr);
         }
 
         self.try_consume(Token::CloseBracket)?;
@@ -306,62 +663,171 @@ impl Parser {
         Ok(Expression::Array(items))
     }
 
+    /// Parses the fragments of the template token at `span`.
+    fn parse_template(
+        &mut self,
+        fragments: Vec<TemplateFragment>,
+        span: &TokenSpan,
+    ) -> ParseResult<Expression> {
+        let mut parts = vec![];
+
+        for fragment in fragments {
+            let part = match fragment {
+                TemplateFragment::Text(text) => TemplatePart::Text(text),
+                TemplateFragment::Expression(tokens) => {
+                    let mut parser = Parser::from_stream(tokens.into_iter().map(Ok));
+                    // Errors in the interpolation are found in whatever the template is in, and
+                    // its custom tokens are handled the same way
+                    parser.contexts = self.contexts.clone();
+                    parser.extensions = self.extensions.clone();
+
+                    let expression = parser.parse_expression().map_err(|mut error| {
+                        // An empty interpolation has no token of its own to point at
+                        if parser.previous.is_none() && error.kind == ParseErrorKind::EndOfInput {
+                            error.span = span.clone();
+                        }
+                        error
+                    })?;
+
+                    // The interpolation must contain exactly one expression
+                    if parser.current_token().is_ok() {
+                        return Err(parser.error(vec![]));
+                    }
+
+                    TemplatePart::Expression(expression)
+                }
+            };
+
+            parts.push(part);
+        }
+
+        Ok(Expression::Template(parts))
+    }
+
     fn parse_variable_assignment(&mut self) -> ParseResult<Expression> {
-        let name = self.identifier_name(self.current_token()?)?;
+        let name = self.identifier_name()?;
 
         self.try_consume(Token::Equals)?;
-        let value = Box::new(self.parse_expression()?);
+        let value = self.parse_expression()?;
 
         Ok(Expression::VariableAssignment { name, value })
     }
 
-    fn parse_return_statement(&mut self) -> ParseResult<Expression> {
+    fn parse_return_statement(&mut self) -> ParseResult<Statement> {
         self.try_consume(Token::Keyword(Keyword::Return))?;
 
         // TODO: check case of empty return;
         let value = self.parse_expression()?;
 
-        Ok(Expression::Return(Box::new(value)))
+        Ok(Statement::Return(value))
     }
 
-    fn parse_variable_decleration(&mut self) -> ParseResult<Expression> {
+    fn parse_variable_decleration(&mut self) -> ParseResult<Statement> {
         self.try_consume(Token::Keyword(Keyword::Let))?;
 
-        let current = self.current_token()?;
-
-        let name = self.identifier_name(current)?.clone();
+        let name = self.identifier_name()?;
 
         self.try_consume(Token::Equals)?;
 
-        let value = Box::new(self.parse_expression()?);
+        let value = self.parse_expression()?;
+
+        Ok(Statement::VariableDecleration {
+            name,
+            value,
+            doc: None,
+        })
+    }
+
+    /// Parses a declaration along with the doc comments in front of it.
+    fn parse_documented(&mut self) -> ParseResult<Statement> {
+        let mut lines = vec![];
+        while let Ok(Token::DocComment(line)) = self.current_token() {
+            lines.push(line);
+            self.consume();
+        }
+
+        let mut declaration = match self.current_token() {
+            Ok(Token::Keyword(Keyword::Let)) => self.parse_variable_decleration(),
+            Ok(Token::Keyword(Keyword::Fun)) => {
+                self.in_context(Construct::Function, Self::parse_function_decleration)
+            }
+            _ => Err(self.error(vec![
+                Expected::Token(Token::Keyword(Keyword::Let)),
+                Expected::Token(Token::Keyword(Keyword::Fun)),
+            ])),
+        }?;
+
+        if let Statement::VariableDecleration { doc, .. }
+        | Statement::FunctionDefinition { doc, .. } = &mut declaration
+        {
+            *doc = Some(lines.join("\n"));
+        }
 
-        Ok(Expression::VariableDecleration { name, value })
+        Ok(declaration)
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::{
-        lexer::{Keyword, Token, TokenSpan, TokenWrapper},
-        parser::Expression,
+        lexer::{
+            tests::{AnnotationTokenizer, DurationTokenizer},
+            Keyword, Lexer, Token, TokenSpan, TokenWrapper,
+        },
+        parser::{BinaryExpression, Expression, Node, Spanned, Statement, TemplatePart},
+    };
+
+    use super::{
+        operator_expression::Associativity, Construct, Context, Expected, ParseErrorKind, Parser,
     };
 
-    use super::Parser;
+    /// A node with no meaningful span, since spans are ignored when comparing nodes.
+    fn node(expression: Expression) -> Node {
+        Box::new(Spanned::new(expression, TokenSpan::default()))
+    }
+
+    fn statement(statement: Statement) -> Spanned<Statement> {
+        Spanned::new(statement, TokenSpan::default())
+    }
+
+    fn expression_statement(expression: Expression) -> Spanned<Statement> {
+        statement(Statement::Expression(node(expression)))
+    }
+
+    /// The expression of an expression statement.
+    fn expression_of(statement: Spanned<Statement>) -> Node {
+        match statement.node {
+            Statement::Expression(expression) => expression,
+            statement => panic!("expected an expression, got {:?}", statement),
+        }
+    }
 
     fn wrap_tokens(tokens: Vec<Token>) -> Vec<TokenWrapper> {
         tokens
             .into_iter()
-            .map(|token| TokenWrapper {
-                token,
-                span: TokenSpan {
-                    line: 0,
-                    start: 0,
+            .map(|token| {
+                let span = TokenSpan {
                     len: 10,
-                },
+                    ..Default::default()
+                };
+
+                TokenWrapper::new(token, span)
             })
             .collect()
     }
 
+    /// The kinds of the syntax errors in `source`.
+    fn error_kinds(source: &str) -> Vec<ParseErrorKind> {
+        let tokens = Lexer::new().tokenize(source).unwrap();
+
+        Parser::new(tokens)
+            .parse()
+            .unwrap_err()
+            .into_iter()
+            .map(|error| error.kind)
+            .collect()
+    }
+
     #[test]
     fn string_variable_decleration() {
         let tokens = wrap_tokens(vec![
@@ -371,13 +837,14 @@ mod test {
             Token::StringLiteral(" ".to_owned()),
         ]);
 
-        let expression = Parser::new(tokens).parse().unwrap().pop().unwrap();
+        let statement = Parser::new(tokens).parse().unwrap().pop().unwrap();
 
         assert_eq!(
-            *expression,
-            Expression::VariableDecleration {
+            statement.node,
+            Statement::VariableDecleration {
                 name: "variable".to_owned(),
-                value: Box::new(Expression::String(" ".to_owned()))
+                value: node(Expression::String(" ".to_owned())),
+                doc: None,
             }
         );
     }
@@ -390,13 +857,13 @@ mod test {
             Token::StringLiteral(" ".to_owned()),
         ]);
 
-        let expression = Parser::new(tokens).parse().unwrap().pop().unwrap();
+        let expression = expression_of(Parser::new(tokens).parse().unwrap().pop().unwrap());
 
         assert_eq!(
-            *expression,
+            expression.node,
             Expression::VariableAssignment {
                 name: "variable".to_owned(),
-                value: Box::new(Expression::String(" ".to_owned()))
+                value: node(Expression::String(" ".to_owned()))
             },
         );
     }
@@ -411,15 +878,680 @@ mod test {
             Token::Keyword(Keyword::End),
         ]);
 
-        let expression = Parser::new(tokens).parse().unwrap().pop().unwrap();
+        let statement = Parser::new(tokens).parse().unwrap().pop().unwrap();
 
         assert_eq!(
-            *expression,
-            Expression::FunctionDefinition {
+            statement.node,
+            Statement::FunctionDefinition {
                 name: "some_function".to_owned(),
                 body: Vec::new(),
                 arguments: Vec::new(),
+                doc: None,
             }
         )
     }
+
+    #[test]
+    fn doc_comments() {
+        let source = "## Adds one.\n##\n## Really.\nfun inc(x) return x + 1 end\n\
+                      # not a doc\n## Dangling\nprint(1)\n## The answer\nlet a = 42\n\
+                      ## Anonymous\nfun(x) end";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+
+        let ast = Parser::new(tokens).parse().unwrap();
+
+        assert!(matches!(
+            &ast[0].node,
+            Statement::FunctionDefinition { doc: Some(doc), .. } if doc == "Adds one.\n\nReally."
+        ));
+        assert!(matches!(
+            &ast[1].node,
+            Statement::Expression(call) if matches!(call.node, Expression::FunctionCall { .. })
+        ));
+        assert!(matches!(
+            &ast[2].node,
+            Statement::VariableDecleration { doc: Some(doc), .. } if doc == "The answer"
+        ));
+        // Anonymous functions are not declarations, so their doc comments are dropped
+        assert_eq!(
+            ast[3],
+            expression_statement(Expression::Function {
+                arguments: vec!["x".to_owned()],
+                body: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn token_stream() {
+        let source = "let a = 1\n## Doubles\nfun double(x)\n\treturn x * 2\nend\n# done\n";
+        let stream = Lexer::new().stream(source.as_bytes());
+
+        let ast = Parser::from_stream(stream).parse().unwrap();
+
+        assert_eq!(ast.len(), 2);
+        assert!(matches!(
+            &ast[1].node,
+            Statement::FunctionDefinition { doc: Some(doc), .. } if doc == "Doubles"
+        ));
+
+        let error = Parser::from_stream(Lexer::new().stream("let a = 1 @".as_bytes()))
+            .parse()
+            .unwrap_err();
+        assert!(matches!(error[..], [ref e] if matches!(e.kind, ParseErrorKind::Lex(_))));
+    }
+
+    /// Writes an expression with every operation in parentheses, to show how it was grouped.
+    fn grouping(expression: &Expression) -> String {
+        let binary = |operator: &str, operation: &BinaryExpression| {
+            format!(
+                "({} {} {})",
+                grouping(&operation.left),
+                operator,
+                grouping(&operation.right)
+            )
+        };
+
+        match expression {
+            Expression::Or(operation) => binary("||", operation),
+            Expression::And(operation) => binary("&&", operation),
+            Expression::LessThan(operation) => binary("<", operation),
+            Expression::LessEquals(operation) => binary("<=", operation),
+            Expression::GreaterThan(operation) => binary(">", operation),
+            Expression::GreaterEquals(operation) => binary(">=", operation),
+            Expression::Addition(operation) => binary("+", operation),
+            Expression::Subtraction(operation) => binary("-", operation),
+            Expression::Multiplication(operation) => binary("*", operation),
+            Expression::Division(operation) => binary("/", operation),
+            Expression::Power(operation) => binary("^", operation),
+            Expression::BoolNegation(operand) => format!("(!{})", grouping(operand)),
+            Expression::Negation(operand) => format!("(-{})", grouping(operand)),
+            Expression::Range {
+                start,
+                end,
+                step,
+                inclusive,
+            } => {
+                let operator = if *inclusive { "..=" } else { ".." };
+                let step = step
+                    .as_ref()
+                    .map(|step| format!(" step {}", grouping(step)))
+                    .unwrap_or_default();
+
+                format!(
+                    "({} {} {}{})",
+                    grouping(start),
+                    operator,
+                    grouping(end),
+                    step
+                )
+            }
+            Expression::FunctionCall { name, arguments } => {
+                let arguments = arguments.iter().map(|a| grouping(a)).collect::<Vec<_>>();
+                format!("{}({})", name, arguments.join(", "))
+            }
+            Expression::ArrayIndexing { identifier, index } => {
+                format!("{}[{}]", grouping(identifier), grouping(index))
+            }
+            Expression::Variable(name) => name.to_owned(),
+            Expression::Number(value) => value.to_string(),
+            _ => panic!("unexpected expression {:?}", expression),
+        }
+    }
+
+    fn parse_grouping(source: &str) -> String {
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let mut ast = Parser::new(tokens).parse().unwrap();
+
+        assert_eq!(ast.len(), 1, "{} is a single expression", source);
+        grouping(&expression_of(ast.pop().unwrap()))
+    }
+
+    /// The binary operators, along with how tightly they bind and how they group.
+    const OPERATORS: &[(&str, u8, Associativity)] = &[
+        ("||", 0, Associativity::Left),
+        ("&&", 1, Associativity::Left),
+        ("<", 2, Associativity::Left),
+        ("<=", 2, Associativity::Left),
+        (">", 2, Associativity::Left),
+        (">=", 2, Associativity::Left),
+        ("..", 3, Associativity::NonAssociative),
+        ("..=", 3, Associativity::NonAssociative),
+        ("+", 4, Associativity::Left),
+        ("-", 4, Associativity::Left),
+        ("*", 5, Associativity::Left),
+        ("/", 5, Associativity::Left),
+        ("^", 6, Associativity::Right),
+    ];
+
+    #[test]
+    fn operator_precedence() {
+        for (first, first_level, associativity) in OPERATORS {
+            let right = *associativity == Associativity::Right;
+
+            for (second, second_level, _) in OPERATORS {
+                let source = format!("a {} b {} c", first, second);
+
+                if first_level == second_level && *associativity == Associativity::NonAssociative {
+                    assert!(
+                        matches!(error_kinds(&source)[..], [ParseErrorKind::InvalidToken(_)]),
+                        "{}",
+                        source
+                    );
+                    continue;
+                }
+
+                let expected =
+                    if first_level > second_level || (first_level == second_level && !right) {
+                        format!("((a {} b) {} c)", first, second)
+                    } else {
+                        format!("(a {} (b {} c))", first, second)
+                    };
+
+                assert_eq!(parse_grouping(&source), expected, "{}", source);
+            }
+
+            // Prefix operators bind tighter than every binary operator but `^`
+            for prefix in ["!", "-"] {
+                let expected = if right {
+                    format!("({}(a {} b))", prefix, first)
+                } else {
+                    format!("(({}a) {} b)", prefix, first)
+                };
+
+                assert_eq!(
+                    parse_grouping(&format!("{}a {} b", prefix, first)),
+                    expected
+                );
+                assert_eq!(
+                    parse_grouping(&format!("a {} {}b", first, prefix)),
+                    format!("(a {} ({}b))", first, prefix)
+                );
+            }
+        }
+
+        assert_eq!(
+            parse_grouping("10 - 2 - 3 * 4 / 2 < 1 + 2 && !x || y && z"),
+            "(((((10 - 2) - ((3 * 4) / 2)) < (1 + 2)) && (!x)) || (y && z))"
+        );
+        assert_eq!(
+            parse_grouping("2 ^ 3 ^ -x * -4 - -(1)"),
+            "(((2 ^ (3 ^ (-x))) * -4) - -1)"
+        );
+        assert_eq!(
+            parse_grouping("i < n - 1..=0 step -k * 2 + 1"),
+            "(i < ((n - 1) ..= 0 step (((-k) * 2) + 1)))"
+        );
+        // `step` is only special right after a range
+        assert_eq!(
+            parse_grouping("step * 2..step step step"),
+            "((step * 2) .. step step step)"
+        );
+        let tokens = Lexer::new()
+            .tokenize("let step = 3\nfor i in 0..10 step step print(i) end")
+            .unwrap();
+        assert!(Parser::new(tokens).parse().is_ok());
+
+        // A `step` on the next line starts a new statement
+        let tokens = Lexer::new().tokenize("let r = 0..10\nstep(2)").unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        assert_eq!(ast.len(), 2);
+        assert!(matches!(
+            &expression_of(ast[1].clone()).node,
+            Expression::FunctionCall { name, .. } if name == "step"
+        ));
+
+        // Ranges don't chain, the second one is rejected rather than grouped either way
+        for source in ["0..10..3", "0..=1 step 2..3", "a..b + 1..=c"] {
+            assert!(
+                matches!(error_kinds(source)[..], [ParseErrorKind::InvalidToken(_)]),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn parenthesized_expressions() {
+        let cases = [
+            ("(a + b) * c", "((a + b) * c)"),
+            ("a - (b - c)", "(a - (b - c))"),
+            ("((a))", "a"),
+            ("!(a && b) || c", "((!(a && b)) || c)"),
+            ("!(a)[0]", "(!a[0])"),
+            ("f((a + b) * 2, (c))[i + 1]", "f(((a + b) * 2), c)[(i + 1)]"),
+            ("m[(i)][j * (k + 1)] / 2", "(m[i][(j * (k + 1))] / 2)"),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(parse_grouping(source), expected, "{}", source);
+        }
+
+        assert_eq!(error_kinds("(a + b"), vec![ParseErrorKind::EndOfInput]);
+    }
+
+    #[test]
+    fn if_branches() {
+        let tokens = Lexer::new()
+            .tokenize("if a 1 elif b 2 elif c else 3 end")
+            .unwrap();
+
+        let expression = expression_of(Parser::new(tokens).parse().unwrap().pop().unwrap());
+
+        let variable = |name: &str| node(Expression::Variable(name.to_owned()));
+        assert_eq!(
+            expression.node,
+            Expression::IfCondition {
+                condition: variable("a"),
+                body: vec![expression_statement(Expression::Number(1.0))],
+                alternative: Some(vec![expression_statement(Expression::IfCondition {
+                    condition: variable("b"),
+                    body: vec![expression_statement(Expression::Number(2.0))],
+                    alternative: Some(vec![expression_statement(Expression::IfCondition {
+                        condition: variable("c"),
+                        body: vec![],
+                        alternative: Some(vec![expression_statement(Expression::Number(3.0))]),
+                    })]),
+                })]),
+            }
+        );
+
+        assert_eq!(
+            error_kinds("if a 1 else 2 elif b 3 end"),
+            vec![ParseErrorKind::InvalidToken(Token::Keyword(Keyword::Elif))]
+        );
+        assert_eq!(
+            error_kinds("if a 1 else 2"),
+            vec![ParseErrorKind::EndOfInput]
+        );
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let tokens = Lexer::new()
+            .tokenize("while a if b break else continue end end")
+            .unwrap();
+
+        let while_loop = Parser::new(tokens).parse().unwrap().pop().unwrap();
+
+        assert_eq!(
+            while_loop.node,
+            Statement::WhileLoop {
+                condition: node(Expression::Variable("a".to_owned())),
+                body: vec![expression_statement(Expression::IfCondition {
+                    condition: node(Expression::Variable("b".to_owned())),
+                    body: vec![statement(Statement::Break)],
+                    alternative: Some(vec![statement(Statement::Continue)]),
+                })],
+            }
+        );
+
+        let outside = [
+            ("break", Keyword::Break),
+            ("if a continue end", Keyword::Continue),
+            ("while a end break", Keyword::Break),
+            ("while a fun f() continue end end", Keyword::Continue),
+        ];
+
+        for (source, keyword) in outside {
+            assert_eq!(
+                error_kinds(source),
+                vec![ParseErrorKind::OutsideLoop(keyword)],
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn for_loops() {
+        let tokens = Lexer::new()
+            .tokenize("for i, x in xs print(i) break end for c in \"abc\" end")
+            .unwrap();
+
+        let ast = Parser::new(tokens).parse().unwrap();
+
+        assert_eq!(
+            ast,
+            vec![
+                statement(Statement::ForLoop {
+                    index: Some("i".to_owned()),
+                    item: "x".to_owned(),
+                    iterable: node(Expression::Variable("xs".to_owned())),
+                    body: vec![
+                        expression_statement(Expression::FunctionCall {
+                            name: "print".to_owned(),
+                            arguments: vec![node(Expression::Variable("i".to_owned()))],
+                        }),
+                        statement(Statement::Break),
+                    ],
+                }),
+                statement(Statement::ForLoop {
+                    index: None,
+                    item: "c".to_owned(),
+                    iterable: node(Expression::String("abc".to_owned())),
+                    body: vec![],
+                }),
+            ]
+        );
+
+        for source in ["for x xs end", "for i, in xs end", "for (x) in xs end"] {
+            assert!(
+                matches!(error_kinds(source)[..], [ParseErrorKind::InvalidToken(_)]),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn statement_placement() {
+        let tokens = Lexer::new()
+            .tokenize("let f = fun(x) return x end\nfun(x) end")
+            .unwrap();
+
+        let ast = Parser::new(tokens).parse().unwrap();
+
+        assert_eq!(
+            ast,
+            vec![
+                statement(Statement::VariableDecleration {
+                    name: "f".to_owned(),
+                    value: node(Expression::Function {
+                        arguments: vec!["x".to_owned()],
+                        body: vec![statement(Statement::Return(node(Expression::Variable(
+                            "x".to_owned()
+                        ))))],
+                    }),
+                    doc: None,
+                }),
+                expression_statement(Expression::Function {
+                    arguments: vec!["x".to_owned()],
+                    body: vec![],
+                }),
+            ]
+        );
+
+        let misplaced = [
+            ("1 + let x = 2", Token::Keyword(Keyword::Let)),
+            ("let x = while a end", Token::Keyword(Keyword::While)),
+            ("print(return 1)", Token::Keyword(Keyword::Return)),
+            ("[1, for x in xs end]", Token::Keyword(Keyword::For)),
+            ("let f = fun g() end", Token::Identifier("g".to_owned())),
+        ];
+
+        for (source, token) in misplaced {
+            assert_eq!(
+                error_kinds(source)[0],
+                ParseErrorKind::InvalidToken(token),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn error_recovery() {
+        let source = "let x = )
+            while a
+                print(1 +)
+                if b
+                    break
+                else
+                    let = 3
+                end
+            end
+            end
+            let y = 2";
+        let tokens = Lexer::new().tokenize(source).unwrap();
+
+        let (ast, errors) = Parser::new(tokens).parse_with_recovery();
+
+        let errors = errors
+            .into_iter()
+            .map(|error| match error.kind {
+                ParseErrorKind::InvalidToken(token) => token,
+                error => panic!("unexpected error {:?}", error),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                Token::CloseParens,
+                Token::CloseParens,
+                Token::Equals,
+                Token::Keyword(Keyword::End)
+            ]
+        );
+
+        let variable = |name: &str| node(Expression::Variable(name.to_owned()));
+        assert_eq!(
+            ast,
+            vec![
+                statement(Statement::Error),
+                statement(Statement::WhileLoop {
+                    condition: variable("a"),
+                    body: vec![
+                        statement(Statement::Error),
+                        expression_statement(Expression::IfCondition {
+                            condition: variable("b"),
+                            body: vec![statement(Statement::Break)],
+                            alternative: Some(vec![statement(Statement::Error)]),
+                        }),
+                    ],
+                }),
+                statement(Statement::Error),
+                statement(Statement::VariableDecleration {
+                    name: "y".to_owned(),
+                    value: node(Expression::Number(2.0)),
+                    doc: None,
+                }),
+            ]
+        );
+
+        // Every block that is still open at the end of the input is missing its `end`
+        let tokens = Lexer::new().tokenize("while a if b print(").unwrap();
+        let (_, errors) = Parser::new(tokens).parse_with_recovery();
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().all(|e| e.kind == ParseErrorKind::EndOfInput));
+    }
+
+    #[test]
+    fn error_messages() {
+        let cases = [
+            (
+                "while a\n  print(1)\n",
+                "unexpected end of input, expected `end` to close `while` opened at line 1",
+            ),
+            (
+                "if a\n  1\nelif b\n  2",
+                "unexpected end of input, expected `end`, `elif` or `else` to close `if` opened at line 1",
+            ),
+            (
+                "let x = [1, 2\nprint(x)\n]]",
+                "unexpected `]`, expected an expression",
+            ),
+            (
+                "while a\n  print(a +)\nend",
+                "unexpected `)`, expected an expression, in function call opened at line 2",
+            ),
+            ("let 1 = 2", "unexpected `1`, expected an identifier"),
+            ("break", "`break` can only be used inside a loop"),
+            (
+                "print(\"x ${}\")",
+                "unexpected end of input, expected an expression, in function call opened at line 1",
+            ),
+        ];
+
+        for (source, message) in cases {
+            let tokens = Lexer::new().tokenize(source).unwrap();
+            let errors = Parser::new(tokens).parse().unwrap_err();
+
+            assert_eq!(errors[0].to_string(), message, "{}", source);
+        }
+
+        let tokens = Lexer::new().tokenize("for x in xs\n  x[1 2]\nend").unwrap();
+        let error = Parser::new(tokens).parse().unwrap_err().remove(0);
+
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::InvalidToken(Token::NumberLiteral(2.0))
+        );
+        assert_eq!(
+            error.span,
+            TokenSpan {
+                line: 1,
+                column: 6,
+                start: 18,
+                len: 1,
+                end_line: 1,
+                end_column: 7,
+                ..Default::default()
+            }
+        );
+        assert_eq!(error.expected, vec![Expected::Token(Token::CloseBracket)]);
+        assert_eq!(
+            error.context,
+            Some(Context {
+                construct: Construct::Indexing,
+                span: TokenSpan {
+                    line: 1,
+                    column: 3,
+                    start: 15,
+                    len: 1,
+                    end_line: 1,
+                    end_column: 4,
+                    ..Default::default()
+                },
+            })
+        );
+
+        // The end of input comes after the display width of the last token, not its bytes
+        let tokens = Lexer::new().tokenize("f(αβγ").unwrap();
+        let error = Parser::new(tokens).parse().unwrap_err().remove(0);
+
+        assert_eq!(error.kind, ParseErrorKind::EndOfInput);
+        assert_eq!((error.span.line, error.span.column), (0, 5));
+
+        let tokens = Lexer::new()
+            .tokenize("let a = \"\"\"\n  x\n\"\"\" +")
+            .unwrap();
+        let error = Parser::new(tokens).parse().unwrap_err().remove(0);
+
+        assert_eq!((error.span.line, error.span.column), (2, 5));
+    }
+
+    #[test]
+    fn custom_tokens() {
+        let lexer = Lexer::builder()
+            .tokenizer(-1, AnnotationTokenizer)
+            .tokenizer(1, DurationTokenizer)
+            .build();
+        let seconds = |text: &str| {
+            let milliseconds = text.trim_end_matches("ms").parse::<f64>().unwrap();
+            Expression::Number(milliseconds / 1000.0)
+        };
+
+        let tokens = lexer.tokenize(r#"sleep(10ms, "${5ms}")"#).unwrap();
+        let expression = expression_of(
+            Parser::new(tokens)
+                .extension("duration", seconds)
+                .parse()
+                .unwrap()
+                .remove(0),
+        );
+
+        assert_eq!(
+            expression.node,
+            Expression::FunctionCall {
+                name: "sleep".to_owned(),
+                arguments: vec![
+                    node(Expression::Number(0.01)),
+                    node(Expression::Template(vec![TemplatePart::Expression(node(
+                        Expression::Number(0.005)
+                    ))])),
+                ],
+            }
+        );
+
+        // Only the kinds with an extension are understood
+        let tokens = lexer.tokenize("let a = @inline").unwrap();
+        let errors = Parser::new(tokens)
+            .extension("duration", seconds)
+            .parse()
+            .unwrap_err();
+
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::UnhandledCustom {
+                kind: "annotation",
+                text: "inline".to_owned()
+            }
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "no parser extension handles the annotation `inline`"
+        );
+    }
+
+    #[test]
+    fn node_spans() {
+        let tokens = Lexer::new()
+            .tokenize("a + b * c\nlet x = (a + b)[0]")
+            .unwrap();
+
+        let ast = Parser::new(tokens).parse().unwrap();
+        let span = |span: &TokenSpan| (span.line, span.column, span.len);
+
+        assert_eq!(span(&ast[0].span), (0, 0, 9));
+        let Statement::Expression(addition) = &ast[0].node else {
+            panic!("expected an expression, got {:?}", ast[0]);
+        };
+        assert_eq!(span(&addition.span), (0, 0, 9));
+        let Expression::Addition(addition) = &addition.node else {
+            panic!("expected an addition, got {:?}", addition);
+        };
+        assert_eq!(span(&addition.left.span), (0, 0, 1));
+        assert_eq!(span(&addition.right.span), (0, 4, 5));
+
+        assert_eq!(span(&ast[1].span), (1, 0, 18));
+        let Statement::VariableDecleration { value, .. } = &ast[1].node else {
+            panic!("expected a declaration, got {:?}", ast[1]);
+        };
+        assert_eq!(span(&value.span), (1, 8, 10));
+        let Expression::ArrayIndexing { identifier, index } = &value.node else {
+            panic!("expected indexing, got {:?}", value);
+        };
+        assert_eq!(span(&identifier.span), (1, 8, 7));
+        assert_eq!(span(&index.span), (1, 16, 1));
+    }
+
+    #[test]
+    fn string_template() {
+        let tokens = Lexer::new().tokenize(r#""sum: ${a + 1}!""#).unwrap();
+
+        let expression = expression_of(Parser::new(tokens).parse().unwrap().pop().unwrap());
+
+        assert_eq!(
+            expression.node,
+            Expression::Template(vec![
+                TemplatePart::Text("sum: ".to_owned()),
+                TemplatePart::Expression(node(Expression::Addition(BinaryExpression::new(
+                    node(Expression::Variable("a".to_owned())),
+                    node(Expression::Number(1.0))
+                )))),
+                TemplatePart::Text("!".to_owned()),
+            ])
+        );
+
+        // An empty interpolation is reported at its template
+        let tokens = Lexer::new()
+            .tokenize("let a = 1\n\nprint(\"x ${}\")")
+            .unwrap();
+        let error = Parser::new(tokens).parse().unwrap_err().remove(0);
+
+        assert_eq!(error.kind, ParseErrorKind::EndOfInput);
+        assert_eq!(error.expected, vec![Expected::Expression]);
+        assert_eq!((error.span.line, error.span.column), (2, 6));
+    }
 }
@@ -0,0 +1,139 @@
+use std::fmt::Display;
+
+use crate::lexer::{token::*, LexError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    InvalidToken(Token),
+    EndOfInput,
+    Lex(LexError),
+    /// A `break` or `continue` that is not inside a loop
+    OutsideLoop(Keyword),
+    /// A token of a custom tokenizer, which no [`Parser::extension`](super::Parser::extension)
+    /// handles
+    UnhandledCustom {
+        kind: &'static str,
+        text: String,
+    },
+}
+
+/// Something the parser would have accepted where an error was found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expected {
+    Token(Token),
+    Identifier,
+    Expression,
+}
+
+/// The constructs an error can be found in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Construct {
+    If,
+    While,
+    For,
+    Function,
+    Call,
+    Array,
+    Parentheses,
+    Indexing,
+}
+
+/// The innermost construct an error was found in, along with the span of the token it was
+/// opened with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Context {
+    pub construct: Construct,
+    pub span: TokenSpan,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: TokenSpan,
+    pub expected: Vec<Expected>,
+    pub context: Option<Context>,
+}
+
+impl Construct {
+    /// The token that closes the construct.
+    pub fn closing(&self) -> Token {
+        match self {
+            Construct::If | Construct::While | Construct::For | Construct::Function => {
+                Token::Keyword(Keyword::End)
+            }
+            Construct::Call | Construct::Parentheses => Token::CloseParens,
+            Construct::Array | Construct::Indexing => Token::CloseBracket,
+        }
+    }
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expected::Token(token) => write!(f, "`{}`", token),
+            Expected::Identifier => write!(f, "an identifier"),
+            Expected::Expression => write!(f, "an expression"),
+        }
+    }
+}
+
+impl Display for Construct {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Construct::If => write!(f, "`if`"),
+            Construct::While => write!(f, "`while`"),
+            Construct::For => write!(f, "`for`"),
+            Construct::Function => write!(f, "`fun`"),
+            Construct::Call => write!(f, "function call"),
+            Construct::Array => write!(f, "array"),
+            Construct::Parentheses => write!(f, "parentheses"),
+            Construct::Indexing => write!(f, "index"),
+        }
+    }
+}
+
+/// Describes the error without its location, like
+/// "unexpected end of input, expected `end` to close `while` opened at line 3".
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::InvalidToken(token) => write!(f, "unexpected `{}`", token)?,
+            ParseErrorKind::EndOfInput => write!(f, "unexpected end of input")?,
+            ParseErrorKind::Lex(error) => return write!(f, "{}", error.kind),
+            ParseErrorKind::OutsideLoop(keyword) => {
+                return write!(f, "`{}` can only be used inside a loop", keyword)
+            }
+            ParseErrorKind::UnhandledCustom { kind, text } => {
+                return write!(f, "no parser extension handles the {} `{}`", kind, text)
+            }
+        }
+
+        if let Some((last, rest)) = self.expected.split_last() {
+            write!(f, ", expected ")?;
+            for (i, expected) in rest.iter().enumerate() {
+                let separator = if i + 1 < rest.len() { ", " } else { " or " };
+                write!(f, "{}{}", expected, separator)?;
+            }
+            write!(f, "{}", last)?;
+        }
+
+        if let Some(context) = &self.context {
+            let closing = Expected::Token(context.construct.closing());
+            let relation = if self.expected.contains(&closing) {
+                " to close"
+            } else {
+                ", in"
+            };
+
+            write!(
+                f,
+                "{} {} opened at line {}",
+                relation,
+                context.construct,
+                context.span.line + 1
+            )?;
+        }
+
+        Ok(())
+    }
+}
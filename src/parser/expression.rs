@@ -1,47 +1,89 @@
-pub type AST = Vec<Box<Expression>>;
+use std::ops::{Deref, DerefMut};
+
+use crate::lexer::TokenSpan;
+
+use super::AST;
+
+/// An expression of the AST, along with where it was parsed from.
+pub type Node = Box<Spanned<Expression>>;
+
+/// A node of the AST along with the span of the source code it was parsed from, which covers
+/// every token of the node. Nodes compare equal when their contents do, wherever they are.
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: TokenSpan,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: TokenSpan) -> Self {
+        Spanned { node, span }
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+impl<T> DerefMut for Spanned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.node
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
-    VariableDecleration {
-        name: String,
-        value: Box<Expression>,
-    },
     VariableAssignment {
         name: String,
-        value: Box<Expression>,
+        value: Node,
     },
-    FunctionDefinition {
-        name: String,
+    /// An anonymous function, as in `fun(x) return x end`
+    Function {
         arguments: Vec<String>,
         body: AST,
     },
     FunctionCall {
         name: String,
-        arguments: AST,
+        arguments: Vec<Node>,
     },
     IfCondition {
-        condition: Box<Expression>,
-        body: AST,
-    },
-    WhileLoop {
-        condition: Box<Expression>,
+        condition: Node,
         body: AST,
+        /// The body of the `else` branch. An `elif` branch is an `if` nested in here.
+        alternative: Option<AST>,
     },
     String(String),
+    Template(Vec<TemplatePart>),
     Number(f64),
     Variable(String),
     Bool(bool),
-    Array(Vec<Box<Expression>>),
+    Array(Vec<Node>),
     ArrayAssignment {
         identifier: String,
-        index: Box<Expression>,
-        value: Box<Expression>,
+        index: Node,
+        value: Node,
     },
     ArrayIndexing {
-        identifier: Box<Expression>,
-        index: Box<Expression>,
+        identifier: Node,
+        index: Node,
+    },
+    Range {
+        start: Node,
+        end: Node,
+        step: Option<Node>,
+        /// Whether `end` is part of the range, as in `a..=b`
+        inclusive: bool,
     },
-    Return(Box<Expression>),
     Addition(BinaryExpression),
     Subtraction(BinaryExpression),
     Multiplication(BinaryExpression),
@@ -50,22 +92,27 @@ pub enum Expression {
     GreaterThan(BinaryExpression),
     GreaterEquals(BinaryExpression),
     Division(BinaryExpression),
+    Power(BinaryExpression),
     Or(BinaryExpression),
     And(BinaryExpression),
-    BoolNegation(Box<Expression>),
+    BoolNegation(Node),
+    Negation(Node),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart {
+    Text(String),
+    Expression(Node),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct BinaryExpression {
-    pub left: Box<Expression>,
-    pub right: Box<Expression>,
+    pub left: Node,
+    pub right: Node,
 }
 
 impl BinaryExpression {
-    pub fn new(left: Expression, right: Expression) -> BinaryExpression {
-        Self {
-            left: Box::new(left),
-            right: Box::new(right),
-        }
+    pub fn new(left: Node, right: Node) -> BinaryExpression {
+        Self { left, right }
     }
 }
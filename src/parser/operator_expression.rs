@@ -0,0 +1,158 @@
+use crate::lexer::token::Token;
+
+use super::{BinaryExpression, Expected, Expression, Node, ParseResult, Parser, Spanned};
+
+/// How tightly operators bind, from the loosest to the tightest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    Lowest,
+    Or,
+    And,
+    Comparison,
+    Range,
+    Sum,
+    Product,
+    Prefix,
+    Power,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Associativity {
+    Left,
+    Right,
+    /// The operator cannot be chained with one of the same precedence, like `0..5..10`
+    NonAssociative,
+}
+
+/// The precedence and associativity of `token` when used as a binary operator.
+pub fn binary_operator(token: &Token) -> Option<(Precedence, Associativity)> {
+    let operator = match token {
+        Token::Or => (Precedence::Or, Associativity::Left),
+        Token::And => (Precedence::And, Associativity::Left),
+        Token::Lt | Token::Lte | Token::Gt | Token::Gte => {
+            (Precedence::Comparison, Associativity::Left)
+        }
+        Token::DotDot | Token::DotDotEq => (Precedence::Range, Associativity::NonAssociative),
+        Token::Plus | Token::Minus => (Precedence::Sum, Associativity::Left),
+        Token::Star | Token::Slash => (Precedence::Product, Associativity::Left),
+        // `2 ^ 3 ^ 2` is `2 ^ 9`, and `-2 ^ 2` is `-4`
+        Token::Caret => (Precedence::Power, Associativity::Right),
+        _ => return None,
+    };
+
+    Some(operator)
+}
+
+fn binary_expression(operator: Token, left: Node, right: Node) -> Expression {
+    let operation = BinaryExpression::new(left, right);
+
+    match operator {
+        Token::Or => Expression::Or(operation),
+        Token::And => Expression::And(operation),
+        Token::Lt => Expression::LessThan(operation),
+        Token::Lte => Expression::LessEquals(operation),
+        Token::Gt => Expression::GreaterThan(operation),
+        Token::Gte => Expression::GreaterEquals(operation),
+        Token::Plus => Expression::Addition(operation),
+        Token::Minus => Expression::Subtraction(operation),
+        Token::Star => Expression::Multiplication(operation),
+        Token::Slash => Expression::Division(operation),
+        Token::Caret => Expression::Power(operation),
+        _ => unreachable!("{:?} is not a binary operator", operator),
+    }
+}
+
+pub trait OperatorExpressionParser {
+    /// Parses an operand followed by any binary operators that bind tighter than `min`, along
+    /// with their right operands.
+    fn parse_binary_expression(&mut self, min: Precedence) -> ParseResult<Node>;
+    fn parse_unary_expression(&mut self) -> ParseResult<Node>;
+}
+
+impl OperatorExpressionParser for Parser<'_> {
+    fn parse_binary_expression(&mut self, min: Precedence) -> ParseResult<Node> {
+        let mut left = self.parse_unary_expression()?;
+        // The precedence of the operator `left` is made of, if any
+        let mut previous = None;
+
+        while let Ok(operator) = self.current_token() {
+            // An operator with the same precedence as the one before it only takes `left` as
+            // its operand when it groups to the right
+            let precedence = match binary_operator(&operator) {
+                Some((precedence, Associativity::Right)) if precedence >= min => precedence,
+                Some((precedence, Associativity::NonAssociative)) if precedence > min => {
+                    if previous == Some(precedence) {
+                        return Err(self.error(vec![]));
+                    }
+                    precedence
+                }
+                Some((precedence, Associativity::Left)) if precedence > min => precedence,
+                _ => break,
+            };
+            previous = Some(precedence);
+
+            self.consume();
+            let start = left.span.clone();
+            let right = self.parse_binary_expression(precedence)?;
+
+            let expression = match operator {
+                Token::DotDot | Token::DotDotEq => {
+                    // `step` is only a keyword right after a range on the same line, it is a
+                    // valid name elsewhere
+                    let end_line = self.previous.as_ref().map(|span| span.line);
+                    let same_line = Some(self.start_span().line) == end_line;
+                    let mut step = None;
+                    if same_line
+                        && matches!(self.current_token(), Ok(Token::Identifier(name)) if name == "step")
+                    {
+                        self.consume();
+                        step = Some(self.parse_binary_expression(precedence)?);
+                    }
+
+                    Expression::Range {
+                        start: left,
+                        end: right,
+                        step,
+                        inclusive: operator == Token::DotDotEq,
+                    }
+                }
+                _ => binary_expression(operator, left, right),
+            };
+            left = self.node_from(expression, &start);
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary_expression(&mut self) -> ParseResult<Node> {
+        let Ok(token) = self.current_token() else {
+            return Err(self.error(vec![Expected::Expression]));
+        };
+        let start = self.start_span();
+
+        let expression = match token {
+            Token::Exclamation => {
+                self.consume();
+                let operand = self.parse_binary_expression(Precedence::Prefix)?;
+
+                Expression::BoolNegation(operand)
+            }
+            Token::Minus => {
+                self.consume();
+                let operand = self.parse_binary_expression(Precedence::Prefix)?;
+
+                // Negative literals are numbers of their own
+                match *operand {
+                    Spanned {
+                        node: Expression::Number(value),
+                        ..
+                    } => Expression::Number(-value),
+                    operand => Expression::Negation(Box::new(operand)),
+                }
+            }
+            _ => return self.parse_operand(),
+        };
+
+        Ok(self.node_from(expression, &start))
+    }
+}
@@ -0,0 +1,41 @@
+use super::{Node, Spanned};
+
+/// The statements of a block or of the program.
+#[allow(clippy::upper_case_acronyms)]
+pub type AST = Vec<Spanned<Statement>>;
+
+/// What a block is made of. Unlike expressions, statements can only appear directly in a block,
+/// so `1 + let x = 2` is not valid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    VariableDecleration {
+        name: String,
+        value: Node,
+        /// The `##` comments right before the declaration
+        doc: Option<String>,
+    },
+    FunctionDefinition {
+        name: String,
+        arguments: Vec<String>,
+        body: AST,
+        /// The `##` comments right before the definition
+        doc: Option<String>,
+    },
+    WhileLoop {
+        condition: Node,
+        body: AST,
+    },
+    ForLoop {
+        /// The variable holding the position of `item`, in `for index, item in ...`
+        index: Option<String>,
+        item: String,
+        iterable: Node,
+        body: AST,
+    },
+    Return(Node),
+    Break,
+    Continue,
+    Expression(Node),
+    /// Stands in for the code that could not be parsed, when parsing with error recovery
+    Error,
+}
@@ -1,25 +1,42 @@
 mod boolean_comparisons;
 mod builtin;
+mod iteration;
 mod math_operations;
 mod scope;
 mod value;
 
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fmt::Display, mem};
 
-use crate::parser::{Expression, AST};
+use crate::{
+    lexer::TokenSpan,
+    parser::{Expression, Spanned, Statement, TemplatePart, AST},
+};
 
 use self::{
     boolean_comparisons::BooleanComparisons,
     builtin::{execute_builtin, is_builtin},
+    iteration::Iteration,
     math_operations::MathOperations,
     scope::Scope,
-    value::{ArrayValue, FunctionValue, Value},
+    value::{ArrayValue, FunctionValue, RangeValue, Value},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Runtime {
     global_scope: Scope,
     local_scope: VecDeque<Scope>,
+    /// Set by `break`, `continue` and `return`, until the loop or the function they belong to
+    /// handles it
+    control_flow: Option<ControlFlow>,
+    /// The span of the node being executed, for error messages
+    span: TokenSpan,
+}
+
+#[derive(Debug, Clone)]
+enum ControlFlow {
+    Break,
+    Continue,
+    Return(Value),
 }
 
 impl Runtime {
@@ -27,42 +44,66 @@ impl Runtime {
         Runtime {
             global_scope: Scope::default(),
             local_scope: VecDeque::default(),
+            control_flow: None,
+            span: TokenSpan::default(),
         }
     }
 
     pub fn run(&mut self, ast: AST) -> Value {
         let mut last_value = Value::Void;
 
-        for expr in ast {
-            if let Expression::Return(inner) = *expr {
-                return self.execute(*inner);
-            }
+        for statement in ast {
+            last_value = self.execute_statement(statement.node);
 
-            last_value = self.execute(*expr);
+            // The rest of the body is skipped until the enclosing loop or function is reached
+            if self.control_flow.is_some() {
+                break;
+            }
         }
 
         last_value
     }
 
+    /// Looks `name` up in the innermost scope first, so that local variables and loop variables
+    /// hide global ones.
     fn try_get_variable(&self, name: &str) -> Option<Value> {
-        self.global_scope
-            .get(name)
-            .or_else(|| self.local_scope.get(0)?.get(name))
-            .map(|v| v.clone())
-    }
-
-    fn mutate_variable<CB>(&mut self, name: &str, mutation_fn: CB)
-    where
-        CB: FnOnce(Value) -> Value,
-    {
-        let value = self.get_variable(name);
-        let result = mutation_fn(value);
-        self.set_variable(name, result);
+        self.local_scope
+            .front()
+            .and_then(|scope| scope.get(name))
+            .or_else(|| self.global_scope.get(name))
+            .cloned()
     }
 
     fn get_variable(&self, name: &str) -> Value {
         self.try_get_variable(name)
-            .expect(&format!("Undefined variable '{}'!", name))
+            .unwrap_or_else(|| self.error(format!("Undefined variable '{}'!", name)))
+    }
+
+    /// Aborts the program, pointing at the node being executed.
+    fn error(&self, message: impl Display) -> ! {
+        panic!(
+            "{} [line: {}, column: {}]",
+            message,
+            self.span.line + 1,
+            self.span.column + 1
+        )
+    }
+
+    /// The positions `range` selects in a collection of `length` elements.
+    fn slice_indices(&self, range: &RangeValue, length: usize) -> Vec<usize> {
+        range.indices(length).unwrap_or_else(|index| {
+            self.error(format!(
+                "IndexError: {} is not a valid index for a length of {}",
+                index, length
+            ))
+        })
+    }
+
+    /// The scope new variables are declared in.
+    fn current_scope(&mut self) -> &mut Scope {
+        self.local_scope
+            .front_mut()
+            .unwrap_or(&mut self.global_scope)
     }
 
     fn set_variable(&mut self, name: &str, value: Value) {
@@ -79,23 +120,91 @@ impl Runtime {
         self.global_scope.set(name, value);
     }
 
-    fn execute(&mut self, expr: Expression) -> Value {
-        match expr {
-            Expression::Variable(name) => self.get_variable(&name),
+    /// Handles the signal the body of a loop left, telling whether the loop has to stop.
+    fn loop_is_over(&mut self) -> bool {
+        match self.control_flow {
+            Some(ControlFlow::Break) => {
+                self.control_flow = None;
+                true
+            }
+            Some(ControlFlow::Continue) => {
+                self.control_flow = None;
+                false
+            }
+            // Left to the function the loop is in
+            Some(ControlFlow::Return(_)) => true,
+            None => false,
+        }
+    }
 
-            Expression::VariableDecleration { name, value } => {
+    fn execute_statement(&mut self, statement: Statement) -> Value {
+        match statement {
+            Statement::VariableDecleration { name, value, .. } => {
                 let value = self.execute(*value);
 
                 self.set_variable(&name, value.clone());
 
                 value
             }
+            Statement::FunctionDefinition {
+                name,
+                body,
+                arguments,
+                ..
+            } => {
+                let value = Value::Function(FunctionValue { body, arguments });
+                self.set_variable(&name, value.clone());
+
+                value
+            }
+            Statement::WhileLoop { condition, body } => {
+                while self.execute(*condition.clone()).is_truthy() {
+                    self.run(body.clone());
+
+                    if self.loop_is_over() {
+                        break;
+                    }
+                }
+
+                Value::Void
+            }
+            Statement::ForLoop { .. } => self.execute_for_loop(statement),
+            Statement::Return(value) => {
+                let value = self.execute(*value);
+                self.control_flow = Some(ControlFlow::Return(value.clone()));
+
+                value
+            }
+            Statement::Break => {
+                self.control_flow = Some(ControlFlow::Break);
+                Value::Void
+            }
+            Statement::Continue => {
+                self.control_flow = Some(ControlFlow::Continue);
+                Value::Void
+            }
+            Statement::Expression(expression) => self.execute(*expression),
+            Statement::Error => unreachable!("programs with syntax errors are not run"),
+        }
+    }
+
+    fn execute(&mut self, node: Spanned<Expression>) -> Value {
+        let span = mem::replace(&mut self.span, node.span);
+        let value = self.evaluate(node.node);
+        self.span = span;
+
+        value
+    }
+
+    fn evaluate(&mut self, expr: Expression) -> Value {
+        match expr {
+            Expression::Variable(name) => self.get_variable(&name),
 
             Expression::VariableAssignment { name, value } => {
                 let value = self.execute(*value);
 
                 if self.try_get_variable(&name).is_none() {
-                    panic!("Undefined variable {}", name);
+                    self.error(format!("Undefined variable {}", name));
                 }
 
                 self.set_variable(&name, value.clone());
@@ -106,7 +215,10 @@ impl Runtime {
             Expression::Addition { .. }
             | Expression::Subtraction { .. }
             | Expression::Multiplication { .. }
-            | Expression::Division { .. } => self.execute_math_operation(expr),
+            | Expression::Division { .. }
+            | Expression::Power { .. } => self.execute_math_operation(expr),
+
+            Expression::Negation(v) => self.execute_negation(*v),
 
             Expression::BoolNegation(v) => {
                 let v = self.execute(*v).is_truthy();
@@ -122,6 +234,20 @@ impl Runtime {
             | Expression::Or { .. } => self.execute_boolean_comparison(expr),
 
             Expression::String(v) => Value::String(v),
+            Expression::Template(parts) => {
+                let mut string = String::new();
+
+                for part in parts {
+                    match part {
+                        TemplatePart::Text(text) => string.push_str(&text),
+                        TemplatePart::Expression(expr) => {
+                            string.push_str(&self.execute(*expr).to_string())
+                        }
+                    }
+                }
+
+                Value::String(string)
+            }
             Expression::Bool(v) => Value::Bool(v),
             Expression::Number(v) => Value::Number(v),
             Expression::ArrayAssignment {
@@ -132,13 +258,14 @@ impl Runtime {
                 let value = self.execute(*value);
                 let index = self.execute(*index);
 
-                self.mutate_variable(&identifier, |v| match (v, index) {
+                let array = match (self.get_variable(&identifier), index) {
                     (Value::Array(mut arr), Value::Number(i)) => {
-                        arr.contents[i as usize] = Box::new(value.clone());
+                        arr.contents[i as usize] = value.clone();
                         Value::Array(arr)
                     }
-                    _ => panic!("{:?} is not an array!", identifier),
-                });
+                    _ => self.error(format!("{:?} is not an array!", identifier)),
+                };
+                self.set_variable(&identifier, array);
 
                 value
             }
@@ -150,18 +277,39 @@ impl Runtime {
                 let index = self.execute(*index);
 
                 match [&value, &index] {
-                    [Value::Array(arr), Value::Number(i)] => *arr.contents[*i as usize].clone(),
-                    _ => panic!(
+                    [Value::Array(arr), Value::Number(i)] => arr.contents[*i as usize].clone(),
+                    [Value::Array(arr), Value::Range(range)] => {
+                        let contents = self
+                            .slice_indices(range, arr.length)
+                            .into_iter()
+                            .map(|i| arr.contents[i].clone())
+                            .collect::<Vec<_>>();
+
+                        Value::Array(ArrayValue {
+                            length: contents.len(),
+                            contents,
+                        })
+                    }
+                    [Value::String(string), Value::Range(range)] => {
+                        let characters = string.chars().collect::<Vec<_>>();
+
+                        Value::String(
+                            self.slice_indices(range, characters.len())
+                                .into_iter()
+                                .map(|i| characters[i])
+                                .collect(),
+                        )
+                    }
+                    _ => self.error(format!(
                         "something went wrong with indexing lol, {:#?}, {:?}",
                         value, index
-                    ),
+                    )),
                 }
             }
             Expression::Array(exprs) => {
                 let values = exprs
                     .into_iter()
                     .map(|expr| self.execute(*expr))
-                    .map(|v| Box::new(v))
                     .collect::<Vec<_>>();
 
                 Value::Array(ArrayValue {
@@ -170,33 +318,50 @@ impl Runtime {
                 })
             }
 
-            Expression::IfCondition { condition, body } => {
+            Expression::IfCondition {
+                condition,
+                body,
+                alternative,
+            } => {
                 if self.execute(*condition).is_truthy() {
-                    self.run(body.clone())
+                    self.run(body)
+                } else if let Some(alternative) = alternative {
+                    self.run(alternative)
                 } else {
                     Value::Void
                 }
             }
 
-            Expression::WhileLoop { condition, body } => {
-                while self.execute(*condition.clone()).is_truthy() {
-                    self.run(body.clone());
-                }
-
-                Value::Void
-            }
-            Expression::FunctionDefinition {
-                name,
-                body,
-                arguments,
+            Expression::Range {
+                start,
+                end,
+                step,
+                inclusive,
             } => {
-                let value = Value::Function(FunctionValue { body, arguments });
+                let start = self.execute(*start);
+                let end = self.execute(*end);
+                let step = step.map_or(Value::Number(1.0), |step| self.execute(*step));
 
-                if !name.is_empty() {
-                    self.set_variable(&name, value.clone());
+                match (start, end, step) {
+                    (_, _, Value::Number(0.0)) => {
+                        self.error("TypeError: the step of a range cannot be 0")
+                    }
+                    (Value::Number(start), Value::Number(end), Value::Number(step)) => {
+                        Value::Range(RangeValue {
+                            start,
+                            end,
+                            step,
+                            inclusive,
+                        })
+                    }
+                    (a, b, c) => self.error(format!(
+                        "TypeError: unable to make a range from {:?} to {:?} with a step of {:?}",
+                        &a, &b, &c
+                    )),
                 }
-
-                value
+            }
+            Expression::Function { body, arguments } => {
+                Value::Function(FunctionValue { body, arguments })
             }
             Expression::FunctionCall { name, arguments } => {
                 let argument_values = arguments
@@ -219,12 +384,72 @@ impl Runtime {
                     let value = self.run(body.clone());
                     self.local_scope.pop_front();
 
-                    value
+                    match self.control_flow.take() {
+                        Some(ControlFlow::Return(value)) => value,
+                        _ => value,
+                    }
                 } else {
-                    panic!("undefined function name {:?}", name);
+                    self.error(format!("undefined function name {:?}", name));
                 }
             }
-            Expression::Return(_) => unreachable!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    use super::Runtime;
+
+    /// Runs `source`, giving the value of its last statement as it would be printed.
+    fn run(source: &str) -> String {
+        let tokens = Lexer::new().tokenize(source).unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+
+        Runtime::new().run(ast).to_string()
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(run("0..10 step 3"), "0..10 step 3");
+        assert_eq!(run("len(0..10 step 3)"), "4");
+        assert_eq!(run("len(0..=10 step 5)"), "3");
+        assert_eq!(run("len(10..0 step 2)"), "0");
+
+        let source = "let total = 0\nfor n in 10..=0 step -5\n  total = total + n\nend\ntotal";
+        assert_eq!(run(source), "15");
+    }
+
+    #[test]
+    fn strings_count_characters() {
+        assert_eq!(run("len(\"αβγ\")"), "3");
+        assert_eq!(run("let s = \"αβγ\"\ns[0..len(s)]"), "αβγ");
+        assert_eq!(run("let s = \"αβγ\"\ns[1..=2]"), "βγ");
+
+        let source =
+            "let reversed = \"\"\nfor c in \"αβγ\"\n  reversed = c + reversed\nend\nreversed";
+        assert_eq!(run(source), "γβα");
+    }
+
+    #[test]
+    fn loop_variables_hide_globals() {
+        let source = "let i = 100
+fun sum(n)
+  let total = 0
+  for i in 0..n
+    total = total + i
+  end
+  return total
+end
+sum(4) + i";
+
+        assert_eq!(run(source), "106");
+    }
+
+    #[test]
+    #[should_panic(expected = "IndexError: 3 is not a valid index for a length of 3 [line: 2")]
+    fn slices_out_of_bounds() {
+        run("let s = \"αβγ\"\ns[0..=len(s)]");
+    }
+}
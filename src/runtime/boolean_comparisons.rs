@@ -36,6 +36,6 @@ impl BooleanComparisons for Runtime {
             (_, _, expr) => unreachable!("invalid boolean comparison with expression {:?}", expr),
         };
 
-        return Value::Bool(boolean_value);
+        Value::Bool(boolean_value)
     }
 }
@@ -10,12 +10,14 @@ pub fn execute_builtin(name: &str, args: Vec<Value>) -> Value {
     match name {
         "len" => match &args[0] {
             Value::Array(a) => Value::Number(a.length as f64),
-            Value::String(ref s) => Value::Number(s.len() as f64),
+            Value::Range(r) => Value::Number(r.len() as f64),
+            // In characters, like indexing and iteration
+            Value::String(ref s) => Value::Number(s.chars().count() as f64),
             a => panic!("object {:?} doen't have a length", a),
         },
         "print" => {
             if let Some(v) = args.first() {
-                println!("{}", v.to_string());
+                println!("{}", v);
             }
 
             Value::Void
@@ -0,0 +1,102 @@
+use crate::parser::Statement;
+
+use super::{
+    value::{ArrayValue, RangeValue, Value},
+    Runtime,
+};
+
+/// The values a `for` loop goes through. A new collection type only has to implement this, and
+/// to be added to [`values_of`].
+pub trait Iterable {
+    fn into_values(self) -> Box<dyn Iterator<Item = Value>>;
+}
+
+impl Iterable for ArrayValue {
+    fn into_values(self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new(self.contents.into_iter())
+    }
+}
+
+impl Iterable for RangeValue {
+    fn into_values(self) -> Box<dyn Iterator<Item = Value>> {
+        Box::new((0..self.len()).map(move |i| Value::Number(self.get(i))))
+    }
+}
+
+/// One string for every character.
+impl Iterable for String {
+    fn into_values(self) -> Box<dyn Iterator<Item = Value>> {
+        let characters = self.chars().collect::<Vec<_>>();
+
+        Box::new(characters.into_iter().map(|c| Value::String(c.to_string())))
+    }
+}
+
+/// The values of `value`, or `value` back if it cannot be iterated over.
+fn values_of(value: Value) -> Result<Box<dyn Iterator<Item = Value>>, Value> {
+    match value {
+        Value::Array(array) => Ok(array.into_values()),
+        Value::Range(range) => Ok(range.into_values()),
+        Value::String(string) => Ok(string.into_values()),
+        a => Err(a),
+    }
+}
+
+pub trait Iteration {
+    fn execute_for_loop(&mut self, statement: Statement) -> Value;
+}
+
+impl Iteration for Runtime {
+    fn execute_for_loop(&mut self, statement: Statement) -> Value {
+        let (index, item, iterable, body) = match statement {
+            Statement::ForLoop {
+                index,
+                item,
+                iterable,
+                body,
+            } => (index, item, iterable, body),
+            _ => unreachable!(),
+        };
+
+        let span = iterable.span.clone();
+        let values = match values_of(self.execute(*iterable)) {
+            Ok(values) => values,
+            Err(a) => {
+                self.span = span;
+                self.error(format!("TypeError: unable to iterate over {:?}", &a))
+            }
+        };
+
+        // The loop variables only exist inside the loop, so the variables they hide are put
+        // back afterwards
+        let names = index.iter().chain([&item]).cloned().collect::<Vec<_>>();
+        let hidden = names
+            .iter()
+            .map(|name| self.current_scope().remove(name))
+            .collect::<Vec<_>>();
+
+        for (i, value) in values.enumerate() {
+            if let Some(index) = &index {
+                self.set_variable(index, Value::Number(i as f64));
+            }
+            self.set_variable(&item, value);
+
+            self.run(body.clone());
+
+            if self.loop_is_over() {
+                break;
+            }
+        }
+
+        for (name, value) in names.into_iter().zip(hidden) {
+            let scope = self.current_scope();
+            scope.remove(&name);
+
+            if let Some(value) = value {
+                scope.set(name, value);
+            }
+        }
+
+        Value::Void
+    }
+}
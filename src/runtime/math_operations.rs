@@ -1,15 +1,19 @@
-use crate::parser::Expression::{self, Addition, Division, Multiplication, Subtraction};
+use crate::parser::{
+    Expression::{self, Addition, Division, Multiplication, Power, Subtraction},
+    Spanned,
+};
 
 use super::{value::Value, Runtime};
 
 pub trait MathOperations {
     fn execute_math_operation(&mut self, expr: Expression) -> Value;
+    fn execute_negation(&mut self, operand: Spanned<Expression>) -> Value;
 }
 
 impl MathOperations for Runtime {
     fn execute_math_operation(&mut self, expr: Expression) -> Value {
         let (left, right) = match expr.clone() {
-            Addition(op) | Subtraction(op) | Multiplication(op) | Division(op) => {
+            Addition(op) | Subtraction(op) | Multiplication(op) | Division(op) | Power(op) => {
                 (self.execute(*op.left), self.execute(*op.right))
             }
             _ => unreachable!(),
@@ -21,27 +25,46 @@ impl MathOperations for Runtime {
                 Value::String(format!("{}{}", a, b))
             }
             (Value::String(a), b, Addition { .. }) => {
-                Value::String(format!("{}{}", a, b.to_string()))
+                Value::String(format!("{}{}", a, b))
             }
             (a, Value::String(b), Addition { .. }) => {
-                Value::String(format!("{}{}", a.to_string(), b))
+                Value::String(format!("{}{}", a, b))
             }
 
-            (a, b, Addition { .. }) => panic!("TypeError: unable to add {:?} and {:?}", &a, &b),
+            (a, b, Addition { .. }) => {
+                self.error(format!("TypeError: unable to add {:?} and {:?}", &a, &b))
+            }
 
             (Value::Number(a), Value::Number(b), Subtraction { .. }) => Value::Number(a - b),
-            (a, b, Subtraction { .. }) => {
-                panic!("TypeError: unable to subtract {:?} and {:?}", &a, &b)
-            }
+            (a, b, Subtraction { .. }) => self.error(format!(
+                "TypeError: unable to subtract {:?} and {:?}",
+                &a, &b
+            )),
 
             (Value::Number(a), Value::Number(b), Multiplication { .. }) => Value::Number(a * b),
-            (a, b, Multiplication { .. }) => {
-                panic!("TypeError: unable to multiply {:?} and {:?}", &a, &b)
-            }
+            (a, b, Multiplication { .. }) => self.error(format!(
+                "TypeError: unable to multiply {:?} and {:?}",
+                &a, &b
+            )),
 
             (Value::Number(a), Value::Number(b), Division { .. }) => Value::Number(a / b),
-            (a, b, Division { .. }) => panic!("TypeError: unable to divide {:?} and {:?}", &a, &b),
+            (a, b, Division { .. }) => {
+                self.error(format!("TypeError: unable to divide {:?} and {:?}", &a, &b))
+            }
+
+            (Value::Number(a), Value::Number(b), Power { .. }) => Value::Number(a.powf(b)),
+            (a, b, Power { .. }) => self.error(format!(
+                "TypeError: unable to raise {:?} to the power of {:?}",
+                &a, &b
+            )),
             _ => unreachable!(),
         }
     }
+
+    fn execute_negation(&mut self, operand: Spanned<Expression>) -> Value {
+        match self.execute(operand) {
+            Value::Number(a) => Value::Number(-a),
+            a => self.error(format!("TypeError: unable to negate {:?}", &a)),
+        }
+    }
 }
@@ -14,4 +14,8 @@ impl Scope {
     pub fn set(&mut self, name: String, value: Value) {
         self.variables.insert(name, value);
     }
+
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        self.variables.remove(name)
+    }
 }
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use crate::parser::AST;
 
 #[derive(Debug, Clone)]
@@ -6,13 +8,14 @@ pub enum Value {
     Bool(bool),
     Function(FunctionValue),
     Number(f64),
+    Range(RangeValue),
     String(String),
     Void,
 }
 
 #[derive(Clone, Debug)]
 pub struct ArrayValue {
-    pub contents: Vec<Box<Value>>,
+    pub contents: Vec<Value>,
     pub length: usize,
 }
 
@@ -22,6 +25,55 @@ pub struct FunctionValue {
     pub body: AST,
 }
 
+/// The numbers from `start` to `end`, `step` apart. They are only computed when they are
+/// needed, so a range takes the same space whatever its length.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeValue {
+    pub start: f64,
+    pub end: f64,
+    pub step: f64,
+    pub inclusive: bool,
+}
+
+impl RangeValue {
+    pub fn len(&self) -> usize {
+        let steps = (self.end - self.start) / self.step;
+
+        let len = if self.inclusive {
+            steps.floor() + 1.0
+        } else {
+            steps.ceil()
+        };
+
+        len.max(0.0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number at `index`, which has to be less than the length of the range.
+    pub fn get(&self, index: usize) -> f64 {
+        self.start + index as f64 * self.step
+    }
+
+    /// The numbers of the range, as positions in a collection of `length` elements, or the
+    /// first number that isn't one.
+    pub fn indices(&self, length: usize) -> Result<Vec<usize>, f64> {
+        (0..self.len())
+            .map(|i| {
+                let index = self.get(i);
+
+                if index < 0.0 || index.fract() != 0.0 || index as usize >= length {
+                    return Err(index);
+                }
+
+                Ok(index as usize)
+            })
+            .collect()
+    }
+}
+
 impl Value {
     pub fn is_truthy(&self) -> bool {
         match *self {
@@ -31,13 +83,25 @@ impl Value {
             _ => panic!("{:?} is not a valid condition", &self),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
-            Value::Bool(true) => "true".to_owned(),
-            Value::Bool(false) => "false".to_owned(),
-            Value::Number(i) => i.to_string(),
-            Value::String(ref v) => v.clone(),
+            Value::Bool(true) => write!(f, "true"),
+            Value::Bool(false) => write!(f, "false"),
+            Value::Number(i) => write!(f, "{}", i),
+            Value::Range(ref r) => {
+                let operator = if r.inclusive { "..=" } else { ".." };
+                write!(f, "{}{}{}", r.start, operator, r.end)?;
+
+                if r.step != 1.0 {
+                    write!(f, " step {}", r.step)?;
+                }
+
+                Ok(())
+            }
+            Value::String(ref v) => write!(f, "{}", v),
             _ => panic!("Value {:?} cannot be converted to string", &self),
         }
     }